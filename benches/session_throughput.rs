@@ -0,0 +1,89 @@
+//! Session store throughput under quiz-night load: many players at once,
+//! each request loading its session, and every fourth one saving changed
+//! answers, like posting a part of the test after viewing a few pages.
+//! Compares SQLite settings used before pool tuning with current defaults.
+//!
+//! Run with `cargo bench --bench session_throughput`.
+
+#[allow(dead_code)]
+#[path = "../src/sqlite.rs"]
+mod sqlite;
+
+use sqlite::SqliteConfig;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tower_sessions::cookie::time::{self, OffsetDateTime};
+use tower_sessions::session::{Id, Record};
+use tower_sessions::session_store::SessionStore;
+use tower_sessions_sqlx_store::SqliteStore;
+
+const PLAYERS: usize = 100;
+const REQUESTS_PER_PLAYER: usize = 200;
+const REQUESTS_PER_SAVE: usize = 4;
+
+async fn run(name: &str, max_connections: u32, wal: bool) {
+    let path = std::env::temp_dir().join(format!("giga-test-bench-{}.db", uuid::Uuid::new_v4()));
+    let config = SqliteConfig {
+        url: format!("sqlite:{}", path.display()),
+        max_connections,
+        wal,
+        busy_timeout: Duration::from_secs(5),
+    };
+    let pool = config.connect().await.expect("failed to open database");
+    let store = SqliteStore::new(pool.clone())
+        .with_table_name("sessions")
+        .expect("invalid table name");
+    store
+        .migrate()
+        .await
+        .expect("failed to create sessions table");
+
+    let start = Instant::now();
+    let mut players = JoinSet::new();
+    for _ in 0..PLAYERS {
+        let store = store.clone();
+        players.spawn(async move {
+            let mut record = Record {
+                id: Id::default(),
+                data: HashMap::new(),
+                expiry_date: OffsetDateTime::now_utc() + time::Duration::days(1),
+            };
+            store.create(&mut record).await.expect("create failed");
+            for i in 0..REQUESTS_PER_PLAYER {
+                let mut record = store
+                    .load(&record.id)
+                    .await
+                    .expect("load failed")
+                    .expect("session missing");
+                if i % REQUESTS_PER_SAVE == 0 {
+                    record
+                        .data
+                        .insert(format!("q1_1_{i}"), serde_json::json!("A"));
+                    store.save(&record).await.expect("save failed");
+                }
+            }
+        });
+    }
+    players.join_all().await;
+    let elapsed = start.elapsed();
+
+    let requests = PLAYERS * REQUESTS_PER_PLAYER;
+    println!(
+        "{name:<36} {requests} requests in {elapsed:>8.2?}, {:>6.0} requests/s",
+        requests as f64 / elapsed.as_secs_f64()
+    );
+    pool.close().await;
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    println!("{PLAYERS} players, {REQUESTS_PER_PLAYER} requests each");
+    run("1 connection, rollback journal (old)", 1, false).await;
+    run("1 connection, WAL", 1, true).await;
+    run("4 connections, WAL (default)", 4, true).await;
+    run("8 connections, WAL", 8, true).await;
+}
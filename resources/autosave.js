@@ -0,0 +1,42 @@
+// Saves each answer as soon as it's picked, so closing the tab doesn't lose
+// a whole part of clicks. Page works the same without JavaScript, and when
+// saving fails "Zapisz odpowiedzi" still sends all answers from the form.
+
+document.querySelectorAll("form[data-autosave]").forEach((form) => {
+    const status = form.querySelector(".autosave-status");
+    const showStatus = (text) => {
+        if (status) {
+            status.textContent = text;
+        }
+    };
+    const save = async (name, value) => {
+        try {
+            const response = await fetch(form.dataset.autosave, {
+                method: "POST",
+                headers: { "X-CSRF-Token": form.elements.csrf_token.value },
+                body: new URLSearchParams({ [name]: value }),
+            });
+            showStatus(response.ok ? "Zapisano" : "Nie zapisano - użyj przycisku");
+        } catch {
+            showStatus("Nie zapisano - użyj przycisku");
+        }
+    };
+    // Requests go one after another, so the last picked answer is saved last
+    let queue = Promise.resolve();
+    form.addEventListener("change", (event) => {
+        const input = event.target;
+        if (input.type === "radio" && input.checked) {
+            queue = queue.then(() => save(input.name, input.value));
+        }
+    });
+    // "wyczyść" buttons would submit the whole form, clear in place instead
+    form.querySelectorAll("button[name=wyczysc]").forEach((button) => {
+        button.addEventListener("click", (event) => {
+            event.preventDefault();
+            form.querySelectorAll(`input[name="${button.value}"]`).forEach((input) => {
+                input.checked = false;
+            });
+            queue = queue.then(() => save(button.name, button.value));
+        });
+    });
+});
@@ -0,0 +1,34 @@
+// Live countdown for timed attempts and release schedule. Server renders
+// remaining time as plain text, so page stays usable without JavaScript;
+// this only makes it tick. When time runs out, answers from the form are
+// saved one last time.
+
+// Keep in sync with `format_duration` in giga_test.rs
+const formatDuration = (left) => {
+    const pad = (n) => String(n).padStart(2, "0");
+    const [days, hours] = [Math.floor(left / 86400), Math.floor(left % 86400 / 3600)];
+    const [minutes, seconds] = [Math.floor(left % 3600 / 60), left % 60];
+    if (days > 0) {
+        return `${days} d ${pad(hours)}:${pad(minutes)}:${pad(seconds)}`;
+    } else if (hours > 0) {
+        return `${hours}:${pad(minutes)}:${pad(seconds)}`;
+    }
+    return `${minutes}:${pad(seconds)}`;
+};
+
+document.querySelectorAll(".countdown[data-seconds-left]").forEach((elem) => {
+    const deadline = Date.now() + Number(elem.dataset.secondsLeft) * 1000;
+    const form = elem.dataset.submitForm ? document.getElementById(elem.dataset.submitForm) : null;
+    const tick = () => {
+        const left = Math.max(0, Math.round((deadline - Date.now()) / 1000));
+        elem.textContent = formatDuration(left);
+        if (left === 0) {
+            clearInterval(timer);
+            if (form) {
+                form.submit();
+            }
+        }
+    };
+    const timer = setInterval(tick, 1000);
+    tick();
+});
@@ -0,0 +1,391 @@
+use crate::db;
+use crate::giga_test::validate_nickname;
+use crate::models::{ResponseKind, SessionResponses};
+use crate::pages::Account;
+use crate::routes::{
+    get_player_id, migrate_session_responses, progress_keys, responses_in_session,
+    save_legacy_responses, GT_FINISHED_KEY, GT_PLAYER_KEY, GT_TIMER_KEY, SESSION_RESPONSE_KEYS,
+};
+use crate::AppState;
+use crate::Error;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use askama::Template;
+use axum::extract::{Form, Request, State};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::LazyLock;
+use tower_sessions::Session;
+
+const GT_ACCOUNT_KEY: &str = "giga_test_account";
+const PASSWORD_MIN_LEN: usize = 8;
+const PASSWORD_MAX_LEN: usize = 128;
+
+/// Values of `routes::progress_keys` entries, as kept in session
+type Progress = Map<String, Value>;
+
+/// Hash that passwords are checked against when username is unknown, so
+/// response time doesn't tell which accounts exist
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password(&uuid::Uuid::new_v4().to_string()).unwrap_or_default());
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct AccountSession {
+    id: i64,
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountForm {
+    username: String,
+    password: String,
+}
+
+async fn logged_in_account(session: &Session) -> Option<AccountSession> {
+    session.get(GT_ACCOUNT_KEY).await.unwrap_or_default()
+}
+
+async fn session_progress(state: &AppState, session: &Session) -> Progress {
+    let mut progress = Progress::new();
+    for key in progress_keys(state) {
+        if let Ok(Some(value)) = session.get::<Value>(key).await {
+            progress.insert(key.to_string(), value);
+        }
+    }
+    progress
+}
+
+async fn restore_progress(state: &AppState, session: &Session, progress: &Progress) {
+    for key in progress_keys(state) {
+        match progress.get(key) {
+            Some(value) => session.insert(key, value).await.unwrap_or_default(),
+            None => {
+                let _ = session.remove::<Value>(key).await;
+            }
+        }
+    }
+}
+
+/// Progress after logging in, and kinds of answers to carry over from
+/// anonymous player. Account progress wins, anonymous answers only fill in
+/// questions not answered on the account yet - answers kept in progress are
+/// merged here, the ones in `responses` table by the caller. Answers from
+/// finished or timed attempts are not carried over - finished ones are
+/// archived anyway.
+fn merge_progress(mut account: Progress, anonymous: Progress) -> (Progress, Vec<ResponseKind>) {
+    if account.is_empty() {
+        return (anonymous, ResponseKind::ALL.to_vec());
+    }
+    let finished = |progress: &Progress| {
+        progress
+            .get(GT_FINISHED_KEY)
+            .and_then(Value::as_bool)
+            .unwrap_or_default()
+    };
+    let timed = |progress: &Progress| progress.get(GT_TIMER_KEY).is_some_and(|t| !t.is_null());
+    let mut kinds = vec![ResponseKind::Practice];
+    if !finished(&account) && !finished(&anonymous) && !timed(&account) && !timed(&anonymous) {
+        kinds.push(ResponseKind::Test);
+    }
+    for (key, kind) in SESSION_RESPONSE_KEYS {
+        if kinds.contains(kind) {
+            merge_answers(&mut account, &anonymous, key);
+        }
+    }
+    (account, kinds)
+}
+
+fn merge_answers(account: &mut Progress, anonymous: &Progress, key: &str) {
+    let answers = |progress: &Progress| {
+        progress
+            .get(key)
+            .and_then(|value| serde_json::from_value::<SessionResponses>(value.clone()).ok())
+            .map(SessionResponses::into_answers)
+            .unwrap_or_default()
+    };
+    let mut answers_merged = answers(anonymous);
+    answers_merged.extend(answers(account));
+    if answers_merged.is_empty() {
+        return;
+    }
+    let merged = SessionResponses::Versioned {
+        answers: answers_merged,
+    };
+    if let Ok(value) = serde_json::to_value(merged) {
+        account.insert(key.to_string(), value);
+    }
+}
+
+/// Move answers saved in account progress by older versions to `responses`
+/// table, unless that's where they are kept now. Returns `true` if progress
+/// changed and should be saved.
+async fn take_legacy_responses(
+    state: &AppState,
+    player_id: &str,
+    progress: &mut Progress,
+) -> Result<bool, Error> {
+    if responses_in_session(state) {
+        return Ok(false);
+    }
+    let mut changed = false;
+    for (key, kind) in SESSION_RESPONSE_KEYS {
+        if let Some(value) = progress.remove(*key) {
+            save_legacy_responses(state, player_id, *kind, value).await?;
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+fn hash_password(password: &str) -> Result<String, Error> {
+    let salt = SaltString::encode_b64(uuid::Uuid::new_v4().as_bytes())
+        .map_err(|e| Error::PasswordHash(e.to_string()))?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::PasswordHash(e.to_string()))
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+async fn start_account_session(session: &Session, account: AccountSession) {
+    // New session ID, so session from before login can't be used to hijack it
+    session.cycle_id().await.unwrap_or_default();
+    session
+        .insert(GT_ACCOUNT_KEY, account)
+        .await
+        .unwrap_or_default();
+}
+
+/// Keeps progress of logged in player in sync with the account, so it
+/// follows the player across devices. Saved progress replaces session
+/// progress before request is handled, and changes are saved after.
+pub(crate) async fn sync_progress(
+    State(state): State<AppState>,
+    session: Session,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(account) = logged_in_account(&session).await else {
+        return next.run(request).await;
+    };
+    let mut saved = match db::get_account_progress(&state.db, account.id).await {
+        Ok(Some(progress)) => serde_json::from_str::<Progress>(&progress).unwrap_or_default(),
+        Ok(None) => {
+            let _ = session.remove::<AccountSession>(GT_ACCOUNT_KEY).await;
+            return next.run(request).await;
+        }
+        Err(e) => {
+            tracing::warn!("Failed to load account progress: {e}");
+            return next.run(request).await;
+        }
+    };
+    let player_id = get_player_id(&session).await;
+    match take_legacy_responses(&state, &player_id, &mut saved).await {
+        Ok(true) => {
+            let progress = Value::Object(saved.clone()).to_string();
+            if let Err(e) = db::set_account_progress(&state.db, account.id, &progress).await {
+                tracing::warn!("Failed to save account progress: {e}");
+            }
+        }
+        Ok(false) => {}
+        Err(e) => tracing::warn!("Failed to move account answers: {e}"),
+    }
+    if session_progress(&state, &session).await != saved {
+        restore_progress(&state, &session, &saved).await;
+    }
+
+    let response = next.run(request).await;
+
+    // Player might have logged out in the meantime
+    if logged_in_account(&session)
+        .await
+        .is_some_and(|a| a.id == account.id)
+    {
+        let progress = session_progress(&state, &session).await;
+        if progress != saved {
+            let progress = Value::Object(progress).to_string();
+            if let Err(e) = db::set_account_progress(&state.db, account.id, &progress).await {
+                tracing::warn!("Failed to save account progress: {e}");
+            }
+        }
+    }
+    response
+}
+
+async fn get_account(session: Session) -> Result<impl IntoResponse, Error> {
+    let account = logged_in_account(&session).await;
+    Ok(Html(
+        Account::new(account.map(|a| a.username).as_deref()).render()?,
+    ))
+}
+
+async fn register(
+    State(state): State<AppState>,
+    session: Session,
+    Form(form): Form<AccountForm>,
+) -> Result<Redirect, Error> {
+    if logged_in_account(&session).await.is_some() {
+        return Ok(Redirect::to("/konto"));
+    }
+    let username = validate_nickname(&form.username)?;
+    if !(PASSWORD_MIN_LEN..=PASSWORD_MAX_LEN).contains(&form.password.chars().count()) {
+        return Err(Error::WrongSize);
+    }
+    let password = form.password;
+    let password_hash = tokio::task::spawn_blocking(move || hash_password(&password)).await??;
+    // Account takes over anonymous player, along with archived attempts
+    let player_id = get_player_id(&session).await;
+    let progress = Value::Object(session_progress(&state, &session).await).to_string();
+    let id = db::insert_account(&state.db, &username, &password_hash, &player_id, &progress)
+        .await?
+        .ok_or(Error::UsernameTaken)?;
+    start_account_session(&session, AccountSession { id, username }).await;
+    Ok(Redirect::to("/konto"))
+}
+
+async fn log_in(
+    State(state): State<AppState>,
+    session: Session,
+    Form(form): Form<AccountForm>,
+) -> Result<Redirect, Error> {
+    if logged_in_account(&session).await.is_some() {
+        return Ok(Redirect::to("/konto"));
+    }
+    // Usernames are stored as registration normalized them
+    let account = match validate_nickname(&form.username) {
+        Ok(username) => db::get_account(&state.db, &username).await?,
+        Err(_) => None,
+    };
+    let password = form.password;
+    let (account, valid) = tokio::task::spawn_blocking(move || {
+        let password_hash = account
+            .as_ref()
+            .map_or(DUMMY_PASSWORD_HASH.as_str(), |account| account.2.as_str());
+        let valid = verify_password(&password, password_hash);
+        (account, valid)
+    })
+    .await?;
+    let Some((id, username, _, player_id, progress)) = account.filter(|_| valid) else {
+        return Err(Error::WrongCredentials);
+    };
+
+    migrate_session_responses(&session, &state).await?;
+    let mut account_progress: Progress = serde_json::from_str(&progress).unwrap_or_default();
+    take_legacy_responses(&state, &player_id, &mut account_progress).await?;
+    let (merged, kinds) =
+        merge_progress(account_progress, session_progress(&state, &session).await);
+    let anonymous_player_id: Option<String> = session.get(GT_PLAYER_KEY).await.unwrap_or_default();
+    if let Some(anonymous_player_id) = anonymous_player_id.filter(|p| *p != player_id) {
+        db::reassign_attempts(&state.db, &anonymous_player_id, &player_id).await?;
+        for kind in kinds {
+            db::merge_responses(&state.db, &anonymous_player_id, &player_id, kind).await?;
+        }
+        db::delete_player_responses(&state.db, &anonymous_player_id).await?;
+    }
+    db::set_account_progress(&state.db, id, &Value::Object(merged.clone()).to_string()).await?;
+    restore_progress(&state, &session, &merged).await;
+    session
+        .insert(GT_PLAYER_KEY, &player_id)
+        .await
+        .unwrap_or_default();
+    start_account_session(&session, AccountSession { id, username }).await;
+    Ok(Redirect::to("/konto"))
+}
+
+async fn log_out(session: Session) -> Redirect {
+    session.flush().await.unwrap_or_default();
+    Redirect::to("/")
+}
+
+pub fn routes(state: &AppState) -> Router<AppState> {
+    // Hashed up front, so the first failed login isn't slower than the rest
+    LazyLock::force(&DUMMY_PASSWORD_HASH);
+    Router::new()
+        .route("/konto", get(get_account))
+        .route("/konto/rejestracja", post(register))
+        .route("/konto/logowanie", post(log_in))
+        .route("/konto/wyloguj", post(log_out))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.max_body_bytes,
+            crate::csrf::protect,
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn progress(value: Value) -> Progress {
+        match value {
+            Value::Object(map) => map,
+            _ => Progress::new(),
+        }
+    }
+
+    #[test]
+    fn test_merge_progress_empty_account() {
+        let anonymous = progress(json!({ GT_FINISHED_KEY: true }));
+        assert_eq!(
+            merge_progress(Progress::new(), anonymous.clone()),
+            (anonymous, ResponseKind::ALL.to_vec())
+        );
+    }
+
+    #[test]
+    fn test_merge_progress_account_wins() {
+        let account = progress(json!({ GT_FINISHED_KEY: false, "giga_test_shuffle_seed": 1 }));
+        let anonymous = progress(json!({ GT_FINISHED_KEY: false, "giga_test_shuffle_seed": 2 }));
+        let (merged, kinds) = merge_progress(account.clone(), anonymous);
+        assert_eq!(merged, account);
+        assert_eq!(kinds, vec![ResponseKind::Practice, ResponseKind::Test]);
+    }
+
+    #[test]
+    fn test_merge_progress_finished_not_carried_over() {
+        let account = progress(json!({ GT_FINISHED_KEY: false }));
+        let anonymous = progress(json!({ GT_FINISHED_KEY: true }));
+        let (merged, kinds) = merge_progress(account, anonymous);
+        assert_eq!(merged[GT_FINISHED_KEY], false);
+        assert_eq!(kinds, vec![ResponseKind::Practice]);
+    }
+
+    #[test]
+    fn test_merge_progress_fills_in_session_answers() {
+        let account = progress(json!({
+            GT_FINISHED_KEY: false,
+            "giga_test_responses": { "answers": { "q1_1_0": "A" } },
+        }));
+        let anonymous = progress(json!({
+            GT_FINISHED_KEY: false,
+            "giga_test_responses": { "answers": { "q1_1_0": "B", "q1_1_1": "C" } },
+            "giga_test_practice_responses": { "answers": { "q1_1_0": "D" } },
+        }));
+        let (merged, _) = merge_progress(account, anonymous);
+        assert_eq!(
+            merged["giga_test_responses"],
+            json!({ "answers": { "q1_1_0": "A", "q1_1_1": "C" } })
+        );
+        assert_eq!(
+            merged["giga_test_practice_responses"],
+            json!({ "answers": { "q1_1_0": "D" } })
+        );
+    }
+
+    #[test]
+    fn test_dummy_password_hash_rejects_passwords() {
+        assert!(PasswordHash::new(&DUMMY_PASSWORD_HASH).is_ok());
+        assert!(!verify_password("", &DUMMY_PASSWORD_HASH));
+        assert!(!verify_password("password123", &DUMMY_PASSWORD_HASH));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::analytics::{self, GIVE_UP_AFTER_SECONDS, MAX_VIEW_SECONDS};
+use crate::db;
+use crate::giga_test::{
+    most_missed_questions, place_bucket_counts, question_stats_csv, question_stats_from_rows,
+};
+use crate::models::LeaderboardPeriod;
+use crate::pages::{AdminAnalytics, AdminDashboard, AdminLeaderboard};
+use crate::AppState;
+use crate::Error;
+use askama::Template;
+use axum::extract::rejection::FormRejection;
+use axum::extract::{Form, FromRequestParts, Path, Query, State};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE, WWW_AUTHENTICATE};
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post, Router};
+use axum_extra::headers::authorization::{Basic, Bearer};
+use axum_extra::headers::Authorization;
+use axum_extra::TypedHeader;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+const MOST_MISSED_QUESTIONS: usize = 10;
+const RECENT_LEADERBOARD_ENTRIES: usize = 10;
+
+/// Extractor guarding admin pages. Uses HTTP basic auth, so browser asks for
+/// credentials on its own; user name is ignored. Scripts can send the same
+/// password as a bearer token instead.
+pub(crate) struct Admin;
+
+impl FromRequestParts<AppState> for Admin {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(password) = &state.admin_password else {
+            return Err(Error::NotFound.into_response());
+        };
+        let basic = TypedHeader::<Authorization<Basic>>::from_request_parts(parts, state).await;
+        let bearer = TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state).await;
+        let given = match (&basic, &bearer) {
+            (Ok(TypedHeader(Authorization(basic))), _) => Some(basic.password()),
+            (_, Ok(TypedHeader(Authorization(bearer)))) => Some(bearer.token()),
+            _ => None,
+        };
+        match given {
+            Some(given) if password_matches(given, password) => Ok(Self),
+            _ => Err((
+                StatusCode::UNAUTHORIZED,
+                [(WWW_AUTHENTICATE, "Basic realm=\"Giga Test admin\"")],
+                "Unauthorized",
+            )
+                .into_response()),
+        }
+    }
+}
+
+/// Compares digests in constant time, so neither the password nor its
+/// length can be guessed from response timing
+fn password_matches(given: &str, password: &str) -> bool {
+    Sha256::digest(given.as_bytes())
+        .ct_eq(&Sha256::digest(password.as_bytes()))
+        .into()
+}
+
+#[derive(Debug, Deserialize)]
+struct PageQuery {
+    /// Counted from 1
+    strona: Option<usize>,
+}
+
+/// Where moderation actions go back to - dashboard or full leaderboard
+#[derive(Debug, Default, Deserialize)]
+struct ModerationForm {
+    #[serde(default)]
+    powrot: String,
+}
+
+impl ModerationForm {
+    fn redirect(form: Result<Form<Self>, FormRejection>) -> Redirect {
+        let form = form.map(|f| f.0).unwrap_or_default();
+        if form.powrot == "/admin" {
+            Redirect::to("/admin")
+        } else {
+            Redirect::to("/admin/ranking")
+        }
+    }
+}
+
+async fn get_dashboard(
+    _admin: Admin,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, Error> {
+    let unexpired_sessions = state.session_store.count_unexpired().await?;
+    let summary = db::get_attempt_summary(&state.db).await?;
+    let place_counts = db::get_place_counts(&state.db).await?;
+    let place_buckets = place_bucket_counts(&place_counts);
+    let rows = db::get_question_stats(&state.db).await?;
+    let stats = question_stats_from_rows(&rows, &state.questions_db);
+    let most_missed = most_missed_questions(&state.questions_db, &stats, MOST_MISSED_QUESTIONS);
+    let entries = db::get_recent_leaderboard_entries(&state.db, RECENT_LEADERBOARD_ENTRIES).await?;
+    let page = AdminDashboard::new(
+        unexpired_sessions,
+        &summary,
+        state.questions_db.len(),
+        &place_buckets,
+        &place_counts,
+        &most_missed,
+        &entries,
+    );
+    Ok(Html(page.render()?))
+}
+
+async fn get_analytics(
+    _admin: Admin,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, Error> {
+    let summary = db::get_analytics_summary(&state.db).await?;
+    let view_times = db::get_part_view_times(&state.db, MAX_VIEW_SECONDS).await?;
+    let answer_counts = db::get_part_answer_counts(&state.db).await?;
+    let give_ups = db::get_part_give_ups(&state.db, GIVE_UP_AFTER_SECONDS).await?;
+    let part_ids: Vec<String> = state
+        .giga_test
+        .iter()
+        .map(|(part_id, _)| part_id.clone())
+        .collect();
+    let parts = analytics::part_analytics(&part_ids, &view_times, &answer_counts, &give_ups);
+    let page = AdminAnalytics::new(state.analytics_retention_days, &summary, &parts);
+    Ok(Html(page.render()?))
+}
+
+async fn purge_sessions(_admin: Admin, State(state): State<AppState>) -> Result<Redirect, Error> {
+    if let Some(deleted) = state.session_store.delete_expired().await? {
+        tracing::info!("deleted {deleted} expired sessions from admin dashboard");
+    }
+    Ok(Redirect::to("/admin"))
+}
+
+async fn get_question_stats_csv(
+    _admin: Admin,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, Error> {
+    let rows = db::get_question_stats(&state.db).await?;
+    let stats = question_stats_from_rows(&rows, &state.questions_db);
+    Ok((
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                CONTENT_DISPOSITION,
+                "attachment; filename=\"giga-test-statystyki.csv\"",
+            ),
+        ],
+        question_stats_csv(&state.questions_db, &stats),
+    ))
+}
+
+async fn get_leaderboard(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Query(query): Query<PageQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let page = query.strona.unwrap_or(1).max(1);
+    let (entries, has_next_page) = db::get_leaderboard(
+        &state.db,
+        LeaderboardPeriod::AllTime,
+        false,
+        None,
+        true,
+        page - 1,
+    )
+    .await?;
+    Ok(Html(
+        AdminLeaderboard::new(&entries, page, has_next_page).render()?,
+    ))
+}
+
+async fn hide_leaderboard_entry(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    form: Result<Form<ModerationForm>, FormRejection>,
+) -> Result<Redirect, Error> {
+    if !db::set_leaderboard_entry_hidden(&state.db, id, true).await? {
+        return Err(Error::NotFound);
+    }
+    Ok(ModerationForm::redirect(form))
+}
+
+async fn show_leaderboard_entry(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    form: Result<Form<ModerationForm>, FormRejection>,
+) -> Result<Redirect, Error> {
+    if !db::set_leaderboard_entry_hidden(&state.db, id, false).await? {
+        return Err(Error::NotFound);
+    }
+    Ok(ModerationForm::redirect(form))
+}
+
+pub fn routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/admin", get(get_dashboard))
+        .route("/admin/sesje/usun-wygasle", post(purge_sessions))
+        .route("/admin/analityka", get(get_analytics))
+        .route("/admin/statystyki.csv", get(get_question_stats_csv))
+        .route("/admin/ranking", get(get_leaderboard))
+        .route("/admin/ranking/{id}/ukryj", post(hide_leaderboard_entry))
+        .route("/admin/ranking/{id}/pokaz", post(show_leaderboard_entry))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.max_body_bytes,
+            crate::csrf::protect,
+        ))
+}
@@ -0,0 +1,180 @@
+use crate::db::{self, PartCountsRow, PartViewTimesRow};
+use crate::giga_test::question_part_id;
+use crate::models::{Event, EventKind, PartAnalytics, UserResponseData};
+use crate::AppState;
+use std::collections::HashMap;
+use std::time::Duration;
+use tower_sessions::Session;
+use tower_sessions_sqlx_store::sqlx::SqlitePool;
+
+const GT_VISITOR_KEY: &str = "giga_test_visitor_id";
+/// Part page views longer than this are counted as this long
+pub(crate) const MAX_VIEW_SECONDS: u64 = 30 * 60;
+/// Visitors who didn't submit and weren't seen for this long gave up
+pub(crate) const GIVE_UP_AFTER_SECONDS: u64 = 24 * 60 * 60;
+const PRUNE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+pub(crate) fn enabled(state: &AppState) -> bool {
+    state.analytics_retention_days.is_some()
+}
+
+/// Random ID events are stored under. Separate from player ID, so events
+/// can't be tied to answers, attempts or account of a player.
+async fn visitor_id(session: &Session) -> String {
+    let visitor_id: Option<String> = session.get(GT_VISITOR_KEY).await.unwrap_or_default();
+    if let Some(visitor_id) = visitor_id {
+        return visitor_id;
+    }
+    let visitor_id = uuid::Uuid::new_v4().to_string();
+    session
+        .insert(GT_VISITOR_KEY, &visitor_id)
+        .await
+        .unwrap_or_default();
+    visitor_id
+}
+
+/// Add events to analytics log, when analytics are enabled. Failures are
+/// only logged, analytics should never break a request.
+pub(crate) async fn record(state: &AppState, session: &Session, events: &[Event]) {
+    if !enabled(state) || events.is_empty() {
+        return;
+    }
+    let visitor_id = visitor_id(session).await;
+    if let Err(e) = db::insert_events(&state.db, &visitor_id, events).await {
+        tracing::warn!("Failed to record analytics events: {e}");
+    }
+}
+
+pub(crate) fn event(kind: EventKind, part_id: Option<&str>) -> Event {
+    Event {
+        kind,
+        part_id: part_id.map(String::from),
+        question_id: None,
+    }
+}
+
+/// Events for answers being saved - first answers and changed ones.
+/// Answers saved again with the same letter are skipped.
+pub(crate) fn answer_events(previous: &UserResponseData, new: &UserResponseData) -> Vec<Event> {
+    let mut question_ids: Vec<&String> = new.keys().collect();
+    question_ids.sort();
+    question_ids
+        .into_iter()
+        .filter_map(|question_id| {
+            let kind = match previous.get(question_id) {
+                None => EventKind::Answer,
+                Some(response) if response.user_answer != new[question_id].user_answer => {
+                    EventKind::AnswerChange
+                }
+                Some(_) => return None,
+            };
+            Some(Event {
+                kind,
+                part_id: question_part_id(question_id).map(String::from),
+                question_id: Some(question_id.clone()),
+            })
+        })
+        .collect()
+}
+
+/// Combine per-part query results into one row for each part of the test
+pub(crate) fn part_analytics(
+    part_ids: &[String],
+    view_times: &[PartViewTimesRow],
+    answer_counts: &[PartCountsRow],
+    give_ups: &[(String, i64)],
+) -> Vec<PartAnalytics> {
+    let to_usize = |value: i64| usize::try_from(value).unwrap_or_default();
+    let mut parts: HashMap<&str, PartAnalytics> = part_ids
+        .iter()
+        .map(|part_id| {
+            let part = PartAnalytics {
+                part_id: part_id.clone(),
+                ..PartAnalytics::default()
+            };
+            (part_id.as_str(), part)
+        })
+        .collect();
+    for (part_id, views, timed_views, seconds) in view_times {
+        if let Some(part) = parts.get_mut(part_id.as_str()) {
+            part.views = to_usize(*views);
+            part.avg_seconds = u64::try_from(*seconds)
+                .ok()
+                .zip(u64::try_from(*timed_views).ok())
+                .and_then(|(seconds, timed_views)| seconds.checked_div(timed_views));
+        }
+    }
+    for (part_id, answers, changes) in answer_counts {
+        if let Some(part) = parts.get_mut(part_id.as_str()) {
+            part.answers = to_usize(*answers);
+            part.changes = to_usize(*changes);
+        }
+    }
+    for (part_id, gave_up) in give_ups {
+        if let Some(part) = parts.get_mut(part_id.as_str()) {
+            part.gave_up = to_usize(*gave_up);
+        }
+    }
+    part_ids
+        .iter()
+        .filter_map(|part_id| parts.remove(part_id.as_str()))
+        .collect()
+}
+
+/// Delete events past retention period every hour, for as long as server runs
+pub(crate) async fn prune_old_events(pool: SqlitePool, retention_days: u64) {
+    let mut interval = tokio::time::interval(PRUNE_PERIOD);
+    loop {
+        interval.tick().await;
+        match db::delete_old_events(&pool, retention_days).await {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!("deleted {deleted} old analytics events"),
+            Err(e) => tracing::warn!("Failed to delete old analytics events: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::responses;
+
+    #[test]
+    fn test_answer_events() {
+        let previous = responses(&[("q1_1_0", 'A'), ("q1_1_1", 'B')]);
+        let new = responses(&[("q1_1_0", 'A'), ("q1_1_1", 'C'), ("q2_1_0", 'D')]);
+        let events = answer_events(&previous, &new);
+        assert_eq!(
+            events,
+            [
+                Event {
+                    kind: EventKind::AnswerChange,
+                    part_id: Some("1".to_string()),
+                    question_id: Some("q1_1_1".to_string()),
+                },
+                Event {
+                    kind: EventKind::Answer,
+                    part_id: Some("2".to_string()),
+                    question_id: Some("q2_1_0".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_part_analytics() {
+        let part_ids = vec!["1".to_string(), "2".to_string()];
+        let parts = part_analytics(
+            &part_ids,
+            &[("1".to_string(), 3, 2, 100), ("9".to_string(), 1, 1, 5)],
+            &[("1".to_string(), 6, 2)],
+            &[("2".to_string(), 4)],
+        );
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].views, 3);
+        assert_eq!(parts[0].avg_seconds, Some(50));
+        assert_eq!(parts[0].change_percent(), 25);
+        assert_eq!(parts[1].avg_seconds, None);
+        assert_eq!(parts[1].gave_up, 4);
+    }
+}
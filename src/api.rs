@@ -0,0 +1,532 @@
+use crate::csrf;
+use crate::giga_test::{responses_from_form_data, validate_form_data};
+use crate::models::{
+    TestStateMainPageElem, TestStateMainPageTotals, TestStatePartPage, TimeLimit, UserResponseData,
+};
+use crate::routes::{self, PartView};
+use crate::AppState;
+use crate::Error;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, Router};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tower_sessions::Session;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::openapi::OpenApi as OpenApiDoc;
+use utoipa::{Modify, OpenApi, ToSchema};
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+use utoipa_scalar::{Scalar, Servable};
+
+const API_TAG: &str = "test";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Giga Test API",
+        version = "1",
+        description = "JSON API for clients other than the browser. Uses the same session \
+            cookie as HTML pages, so clients have to keep cookies between requests."
+    ),
+    modifiers(&SessionCookie),
+    security(("session_cookie" = []))
+)]
+struct ApiDoc;
+
+struct SessionCookie;
+
+impl Modify for SessionCookie {
+    fn modify(&self, openapi: &mut OpenApiDoc) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "session_cookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("giga_test_session"))),
+        );
+        components.add_security_scheme(
+            "csrf_token",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "X-CSRF-Token",
+                "Token from `GET /api/v1/csrf-token`, required by requests that change anything",
+            ))),
+        );
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ErrorBody {
+    error: String,
+}
+
+/// Error of an API request, sent as JSON object with `error` message and
+/// the same status code HTML pages use
+pub(crate) struct ApiError(Error);
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        Self(error)
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self(Error::InvalidJson(rejection.body_text()))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.0.to_string(),
+        };
+        (self.0.status_code(), Json(body)).into_response()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct CsrfToken {
+    token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct PartSummary {
+    id: String,
+    magazine_issue: String,
+    magazine_date: String,
+    total_q: usize,
+    answered_q: usize,
+    /// Only known once test is finished
+    answered_good_q: Option<usize>,
+    /// Only known once test is finished
+    answered_bad_q: Option<usize>,
+    /// Set only in release schedule mode
+    seconds_to_unlock: Option<u64>,
+    /// Set only in release schedule mode
+    seconds_to_deadline: Option<u64>,
+}
+
+impl PartSummary {
+    fn new(part: TestStateMainPageElem, test_finished: bool) -> Self {
+        Self {
+            id: part.test_id,
+            magazine_issue: part.pe_id,
+            magazine_date: part.pe_date,
+            total_q: part.total_q,
+            answered_q: part.answered_q,
+            answered_good_q: test_finished.then_some(part.answered_good_q),
+            answered_bad_q: test_finished.then_some(part.answered_bad_q),
+            seconds_to_unlock: part.seconds_to_unlock,
+            seconds_to_deadline: part.seconds_to_deadline,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Parts {
+    test_finished: bool,
+    count_canceled: bool,
+    parts: Vec<PartSummary>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Choice {
+    #[schema(value_type = String, example = "A")]
+    id: char,
+    /// HTML
+    answer: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Question {
+    id: String,
+    /// HTML
+    question: String,
+    canceled: bool,
+    choices: Vec<Choice>,
+    #[schema(value_type = Option<String>, example = "A")]
+    user_answer: Option<char>,
+    /// Only sent once test is finished
+    #[schema(value_type = Option<String>, example = "B")]
+    correct_answer: Option<char>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Section {
+    /// HTML
+    introduction: String,
+    questions: Vec<Question>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Part {
+    id: String,
+    /// HTML
+    introduction: String,
+    test_finished: bool,
+    /// Set only in timed attempts
+    seconds_left: Option<u64>,
+    sections: Vec<Section>,
+}
+
+impl Part {
+    fn new(
+        id: String,
+        part_state: TestStatePartPage,
+        test_finished: bool,
+        seconds_left: Option<u64>,
+    ) -> Self {
+        let sections = part_state
+            .sections
+            .into_iter()
+            .map(|section| Section {
+                introduction: section.introduction,
+                questions: section
+                    .questions
+                    .into_iter()
+                    .map(|question| Question {
+                        correct_answer: question.correct_answer().filter(|_| test_finished),
+                        id: question.id,
+                        question: question.question,
+                        canceled: question.canceled,
+                        choices: question
+                            .choices
+                            .into_iter()
+                            .map(|(id, choice)| Choice {
+                                id,
+                                answer: choice.answer,
+                            })
+                            .collect(),
+                        user_answer: question.user_answer,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self {
+            id,
+            introduction: part_state.introduction,
+            test_finished,
+            seconds_left,
+            sections,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Totals {
+    count_canceled: bool,
+    #[serde(flatten)]
+    #[schema(inline)]
+    totals: TestStateMainPageTotals,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct Answers {
+    /// Question ID to picked letter
+    #[schema(value_type = HashMap<String, String>, example = json!({"q1_1_0": "A"}))]
+    answers: HashMap<String, char>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+struct Restart {
+    #[serde(default)]
+    time_limit: Option<TimeLimit>,
+}
+
+/// Token to send in `X-CSRF-Token` header of requests that change anything
+#[utoipa::path(
+    get,
+    path = "/api/v1/csrf-token",
+    tag = API_TAG,
+    responses((status = OK, body = CsrfToken))
+)]
+async fn get_csrf_token(session: Session) -> Json<CsrfToken> {
+    Json(CsrfToken {
+        token: csrf::session_token(&session).await,
+    })
+}
+
+/// All parts, with number of questions and answers
+#[utoipa::path(
+    get,
+    path = "/api/v1/parts",
+    tag = API_TAG,
+    responses((status = OK, body = Parts))
+)]
+async fn get_parts(
+    State(state): State<AppState>,
+    session: Session,
+) -> Result<Json<Parts>, ApiError> {
+    let (parts, _) = routes::load_index(&state, &session).await?;
+    let test_finished = routes::get_test_finished(&session).await;
+    Ok(Json(Parts {
+        test_finished,
+        count_canceled: routes::get_count_canceled(&session).await,
+        parts: parts
+            .into_iter()
+            .map(|part| PartSummary::new(part, test_finished))
+            .collect(),
+    }))
+}
+
+/// Introduction, sections and questions of a part. Opening a part starts
+/// its timer in per-part timed attempts.
+#[utoipa::path(
+    get,
+    path = "/api/v1/parts/{id}",
+    tag = API_TAG,
+    params(("id" = String, Path, description = "Part ID")),
+    responses(
+        (status = OK, body = Part),
+        (status = FORBIDDEN, description = "Part is not available yet", body = ErrorBody),
+        (status = NOT_FOUND, description = "No such part", body = ErrorBody)
+    )
+)]
+async fn get_part(
+    State(state): State<AppState>,
+    session: Session,
+    Path(id): Path<String>,
+) -> Result<Json<Part>, ApiError> {
+    match routes::load_part(&state, &session, &id, false).await? {
+        PartView::Locked(_) => Err(Error::PartLocked.into()),
+        PartView::Open {
+            part_state,
+            test_finished,
+            seconds_left,
+        } => Ok(Json(Part::new(id, part_state, test_finished, seconds_left))),
+    }
+}
+
+/// Save answers. Nothing is saved when any question or letter is unknown.
+#[utoipa::path(
+    post,
+    path = "/api/v1/answers",
+    tag = API_TAG,
+    security(("session_cookie" = [], "csrf_token" = [])),
+    request_body = Answers,
+    responses(
+        (status = NO_CONTENT, description = "Answers saved"),
+        (status = BAD_REQUEST, description = "Invalid JSON, unknown question or letter", body = ErrorBody),
+        (status = FORBIDDEN, description = "Test is finished, part is locked, time is up or CSRF token is wrong", body = ErrorBody)
+    )
+)]
+async fn post_answers(
+    State(state): State<AppState>,
+    session: Session,
+    body: Result<Json<Answers>, JsonRejection>,
+) -> Result<StatusCode, ApiError> {
+    let form_data: HashMap<String, String> = body?
+        .0
+        .answers
+        .into_iter()
+        .map(|(question_id, answer)| (question_id, answer.to_string()))
+        .collect();
+    validate_form_data(&form_data, &state.giga_test)?;
+    let new_responses: UserResponseData = responses_from_form_data(&form_data, &state.questions_db);
+    routes::save_test_answers(&state, &session, &new_responses).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn load_totals(state: &AppState, session: &Session) -> Result<Json<Totals>, ApiError> {
+    let (_, totals) = routes::load_index(state, session).await?;
+    Ok(Json(Totals {
+        count_canceled: routes::get_count_canceled(session).await,
+        totals,
+    }))
+}
+
+/// Remove answer to a question, so it counts as not answered again
+#[utoipa::path(
+    delete,
+    path = "/api/v1/answers/{question_id}",
+    tag = API_TAG,
+    security(("session_cookie" = [], "csrf_token" = [])),
+    params(("question_id" = String, Path, description = "Question ID, like `q1_1_0`")),
+    responses(
+        (status = NO_CONTENT, description = "Answer removed, or there was none"),
+        (status = BAD_REQUEST, description = "Unknown question", body = ErrorBody),
+        (status = FORBIDDEN, description = "Test is finished, part is locked, time is up or CSRF token is wrong", body = ErrorBody)
+    )
+)]
+async fn delete_answer(
+    State(state): State<AppState>,
+    session: Session,
+    Path(question_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    routes::clear_test_answer(&state, &session, &question_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Correct answers and place of a finished attempt
+#[utoipa::path(
+    get,
+    path = "/api/v1/totals",
+    tag = API_TAG,
+    responses(
+        (status = OK, body = Totals),
+        (status = BAD_REQUEST, description = "Test is not finished", body = ErrorBody)
+    )
+)]
+async fn get_totals(
+    State(state): State<AppState>,
+    session: Session,
+) -> Result<Json<Totals>, ApiError> {
+    if !routes::get_test_finished(&session).await {
+        return Err(Error::TestNotFinished.into());
+    }
+    load_totals(&state, &session).await
+}
+
+/// Finish the attempt
+#[utoipa::path(
+    post,
+    path = "/api/v1/finish",
+    tag = API_TAG,
+    security(("session_cookie" = [], "csrf_token" = [])),
+    responses(
+        (status = OK, body = Totals),
+        (status = FORBIDDEN, description = "CSRF token is wrong", body = ErrorBody)
+    )
+)]
+async fn post_finish(
+    State(state): State<AppState>,
+    session: Session,
+) -> Result<Json<Totals>, ApiError> {
+    routes::finish_test(&state, &session).await?;
+    load_totals(&state, &session).await
+}
+
+/// Start a new attempt, optionally with a time limit
+#[utoipa::path(
+    post,
+    path = "/api/v1/restart",
+    tag = API_TAG,
+    security(("session_cookie" = [], "csrf_token" = [])),
+    request_body(content = Option<Restart>),
+    responses(
+        (status = NO_CONTENT, description = "New attempt started"),
+        (status = BAD_REQUEST, description = "Invalid JSON or time limit", body = ErrorBody),
+        (status = FORBIDDEN, description = "CSRF token is wrong", body = ErrorBody)
+    )
+)]
+async fn post_restart(
+    State(state): State<AppState>,
+    session: Session,
+    body: Result<Option<Json<Restart>>, JsonRejection>,
+) -> Result<StatusCode, ApiError> {
+    let restart = body?.map(|json| json.0).unwrap_or_default();
+    let time_limit = restart
+        .time_limit
+        .map(routes::check_time_limit)
+        .transpose()?;
+    routes::restart_test(&state, &session, time_limit, false).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Requests that change anything have to carry session's CSRF token in a
+/// header, so other sites can't make player's browser send them
+async fn protect(session: Session, request: Request, next: Next) -> Response {
+    if !request.method().is_safe() {
+        if let Err(e) = csrf::verify_header(&session, request.headers()).await {
+            return ApiError(e).into_response();
+        }
+    }
+    next.run(request).await
+}
+
+fn api_router() -> OpenApiRouter<AppState> {
+    let mut doc = ApiDoc::openapi();
+    // Filled from Cargo.toml, which has no license
+    doc.info.license = None;
+    OpenApiRouter::with_openapi(doc)
+        .routes(routes!(get_csrf_token))
+        .routes(routes!(get_parts))
+        .routes(routes!(get_part))
+        .routes(routes!(post_answers))
+        .routes(routes!(delete_answer))
+        .routes(routes!(post_finish))
+        .routes(routes!(post_restart))
+        .routes(routes!(get_totals))
+}
+
+/// JSON API for clients other than the browser. Uses the same session
+/// cookie as HTML pages. Choices are always in original order, even if
+/// player asked for shuffled ones on the website.
+pub fn routes() -> Router<AppState> {
+    let (router, spec) = api_router().split_for_parts();
+    router
+        .route_layer(axum::middleware::from_fn(protect))
+        .merge(Scalar::with_url("/api/docs", spec.clone()))
+        .route("/api/openapi.json", get(Json(spec)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        TestStatePartPageAnswerChoice, TestStatePartPageQuestion, TestStatePartPageSection,
+    };
+    use std::collections::BTreeMap;
+
+    const OPENAPI_SNAPSHOT: &str = include_str!("../openapi.json");
+
+    fn part_state() -> TestStatePartPage {
+        let choice = |answer: &str, correct: bool| TestStatePartPageAnswerChoice {
+            answer: answer.to_string(),
+            correct,
+            ..TestStatePartPageAnswerChoice::default()
+        };
+        TestStatePartPage {
+            introduction: "Part".to_string(),
+            sections: vec![TestStatePartPageSection {
+                introduction: "Section".to_string(),
+                questions: vec![TestStatePartPageQuestion {
+                    id: "q1_1_0".to_string(),
+                    question: "Question".to_string(),
+                    choices: BTreeMap::from([
+                        ('A', choice("bad", false)),
+                        ('B', choice("good", true)),
+                    ]),
+                    user_answer: Some('A'),
+                    ..TestStatePartPageQuestion::default()
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_part_hides_correct_answer_until_finished() {
+        let part = Part::new("1".to_string(), part_state(), false, None);
+        let question = &part.sections[0].questions[0];
+        assert_eq!(question.correct_answer, None);
+        assert_eq!(question.user_answer, Some('A'));
+        assert_eq!(question.choices[1].id, 'B');
+        let json = serde_json::to_value(&part).unwrap();
+        assert!(!json.to_string().contains("\"correct\""));
+
+        let part = Part::new("1".to_string(), part_state(), true, None);
+        assert_eq!(part.sections[0].questions[0].correct_answer, Some('B'));
+    }
+
+    #[test]
+    fn test_openapi_matches_snapshot() {
+        let spec = api_router().split_for_parts().1.to_pretty_json().unwrap() + "\n";
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json"), &spec).unwrap();
+            return;
+        }
+        assert!(
+            spec == OPENAPI_SNAPSHOT,
+            "openapi.json is out of date, run tests with UPDATE_OPENAPI=1 and commit the changes"
+        );
+        for path in ["/api/v1/parts", "/api/v1/parts/{id}", "/api/v1/totals"] {
+            assert!(
+                OPENAPI_SNAPSHOT.contains(&format!("\"{path}\"")),
+                "{path} missing"
+            );
+        }
+    }
+}
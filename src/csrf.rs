@@ -0,0 +1,243 @@
+use crate::Error;
+use axum::body::{to_bytes, Body, Bytes};
+use axum::extract::{FromRequest, Multipart, Request, State};
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http_body_util::LengthLimitError;
+use std::cell::Cell;
+use subtle::ConstantTimeEq;
+use tower_sessions::Session;
+
+const GT_CSRF_KEY: &str = "giga_test_csrf_token";
+/// Name of hidden form field with the token
+pub(crate) const CSRF_FIELD: &str = "csrf_token";
+/// Header scripts and API clients send the token in
+const CSRF_HEADER: &str = "x-csrf-token";
+
+struct RequestToken {
+    value: String,
+    /// Set once a template embeds the token, so it's only saved in session
+    /// of visitors who got a form
+    used: Cell<bool>,
+}
+
+tokio::task_local! {
+    static TOKEN: RequestToken;
+}
+
+/// Token of current session, for templates to put in their forms
+pub(crate) fn token() -> String {
+    TOKEN
+        .try_with(|token| {
+            token.used.set(true);
+            token.value.clone()
+        })
+        .unwrap_or_default()
+}
+
+/// Check token of requests that change state, and make it available to
+/// templates of all requests. Token field is removed from URL-encoded
+/// forms before handlers see them. Forms are read up to `max_body_bytes`.
+pub(crate) async fn protect(
+    State(max_body_bytes): State<usize>,
+    session: Session,
+    request: Request,
+    next: Next,
+) -> Response {
+    let saved: Option<String> = session.get(GT_CSRF_KEY).await.unwrap_or_default();
+    let request = if request.method().is_safe() {
+        request
+    } else {
+        match verify(saved.as_deref(), request, max_body_bytes).await {
+            Ok(request) => request,
+            Err(e) => return e.into_response(),
+        }
+    };
+    let token = RequestToken {
+        value: saved.clone().unwrap_or_else(new_token),
+        used: Cell::new(false),
+    };
+    let (response, token) = TOKEN
+        .scope(token, async {
+            let response = next.run(request).await;
+            (
+                response,
+                TOKEN.with(|token| (token.value.clone(), token.used.get())),
+            )
+        })
+        .await;
+    if let (None, (value, true)) = (saved, token) {
+        session.insert(GT_CSRF_KEY, value).await.unwrap_or_default();
+    }
+    response
+}
+
+fn new_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Token of the session, saved in it if it had none yet. For API clients,
+/// which send it in a header rather than get it in forms.
+pub(crate) async fn session_token(session: &Session) -> String {
+    let saved: Option<String> = session.get(GT_CSRF_KEY).await.unwrap_or_default();
+    if let Some(saved) = saved {
+        return saved;
+    }
+    let token = new_token();
+    session
+        .insert(GT_CSRF_KEY, &token)
+        .await
+        .unwrap_or_default();
+    token
+}
+
+/// Check token of a request that can only carry it in a header
+pub(crate) async fn verify_header(session: &Session, headers: &HeaderMap) -> Result<(), Error> {
+    let saved: Option<String> = session.get(GT_CSRF_KEY).await.unwrap_or_default();
+    match (saved, headers.get(CSRF_HEADER)) {
+        (Some(saved), Some(sent)) if token_matches(sent.as_bytes(), &saved) => Ok(()),
+        _ => Err(Error::CsrfTokenMismatch),
+    }
+}
+
+/// Compared in constant time, so the token can't be guessed byte by byte
+fn token_matches(sent: &[u8], saved: &str) -> bool {
+    sent.ct_eq(saved.as_bytes()).into()
+}
+
+async fn verify(
+    saved: Option<&str>,
+    request: Request,
+    max_body_bytes: usize,
+) -> Result<Request, Error> {
+    let saved = saved.ok_or(Error::CsrfTokenMismatch)?;
+    if let Some(sent) = request.headers().get(CSRF_HEADER) {
+        return if token_matches(sent.as_bytes(), saved) {
+            Ok(request)
+        } else {
+            Err(Error::CsrfTokenMismatch)
+        };
+    }
+    let content_type = content_type(request.headers());
+    let (mut parts, body) = request.into_parts();
+    let bytes = to_bytes(body, max_body_bytes).await.map_err(|e| {
+        if is_length_limit(&e) {
+            Error::PayloadTooLarge
+        } else {
+            Error::WrongSize
+        }
+    })?;
+    let (sent, body) = if content_type.starts_with("application/x-www-form-urlencoded") {
+        let (sent, rest) = take_form_field(&bytes, CSRF_FIELD);
+        parts.headers.remove(CONTENT_LENGTH);
+        (sent, Body::from(rest))
+    } else if content_type.starts_with("multipart/form-data") {
+        (
+            multipart_field(&content_type, bytes.clone(), CSRF_FIELD).await,
+            Body::from(bytes),
+        )
+    } else {
+        (None, Body::from(bytes))
+    };
+    if !sent.is_some_and(|sent| token_matches(sent.as_bytes(), saved)) {
+        return Err(Error::CsrfTokenMismatch);
+    }
+    Ok(Request::from_parts(parts, body))
+}
+
+/// Whether reading the body failed because it's over a limit, either ours or
+/// one of a layer in front of us
+fn is_length_limit(error: &axum::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(error) = source {
+        if error.is::<LengthLimitError>() {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+fn content_type(headers: &HeaderMap) -> String {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Value of a field of URL-encoded form, and the form without that field
+fn take_form_field(form: &[u8], name: &str) -> (Option<String>, String) {
+    let mut value = None;
+    let mut rest = form_urlencoded::Serializer::new(String::new());
+    for (key, field_value) in form_urlencoded::parse(form) {
+        if key == name {
+            value = Some(field_value.into_owned());
+        } else {
+            rest.append_pair(&key, &field_value);
+        }
+    }
+    (value, rest.finish())
+}
+
+async fn multipart_field(content_type: &str, body: Bytes, name: &str) -> Option<String> {
+    let content_type = HeaderValue::from_str(content_type).ok()?;
+    let request = Request::builder()
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .ok()?;
+    let mut multipart = Multipart::from_request(request, &()).await.ok()?;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        if field.name() == Some(name) {
+            return field.text().await.ok();
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_form_field() {
+        let (token, rest) = take_form_field(b"q1_1_0=A&csrf_token=abc&q1_1_1=B+C", CSRF_FIELD);
+        assert_eq!(token.as_deref(), Some("abc"));
+        assert_eq!(rest, "q1_1_0=A&q1_1_1=B+C");
+        let (token, rest) = take_form_field(b"wyczysc=q1_1_0", CSRF_FIELD);
+        assert_eq!(token, None);
+        assert_eq!(rest, "wyczysc=q1_1_0");
+    }
+
+    fn form_request(body: &'static str) -> Request {
+        Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verify_form() {
+        let request = verify(Some("abc"), form_request("q1_1_0=A&csrf_token=abc"), 64)
+            .await
+            .unwrap();
+        let body = to_bytes(request.into_body(), 64).await.unwrap();
+        assert_eq!(body, "q1_1_0=A");
+        assert!(matches!(
+            verify(Some("abc"), form_request("q1_1_0=A&csrf_token=abd"), 64).await,
+            Err(Error::CsrfTokenMismatch)
+        ));
+        assert!(matches!(
+            verify(Some("abc"), form_request("q1_1_0=A&csrf_token=abc"), 8).await,
+            Err(Error::PayloadTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_token_outside_request_is_empty() {
+        assert_eq!(token(), "");
+    }
+}
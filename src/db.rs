@@ -0,0 +1,1328 @@
+use crate::models::{
+    AnalyticsSummary, Attempt, AttemptSummary, Event, EventKind, LeaderboardEntry,
+    LeaderboardPeriod, ResponseKind, ResultSnapshot, TestStateMainPageTotals, TimeLimit,
+    UserResponseData, RESPONSES_SCHEMA_VERSION,
+};
+use std::collections::BTreeMap;
+use tower_sessions_sqlx_store::sqlx::{self, SqlitePool};
+
+pub(crate) type QuestionStatsRow = (String, String, i64);
+/// Part ID and two counts, meaning depends on query
+pub(crate) type PartCountsRow = (String, i64, i64);
+/// Part ID, views, timed views and their total seconds
+pub(crate) type PartViewTimesRow = (String, i64, i64, i64);
+/// Question ID and picked letter
+pub(crate) type ResponseRow = (String, String);
+
+fn to_i64(value: usize) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+fn to_usize(value: i64) -> usize {
+    usize::try_from(value).unwrap_or_default()
+}
+
+/// SQLite has no unsigned integers, so seeds are stored bit for bit -
+/// seeds above `i64::MAX` come out negative and [`seed_from_i64`] restores them
+fn seed_to_i64(seed: u64) -> i64 {
+    i64::from_ne_bytes(seed.to_ne_bytes())
+}
+
+fn seed_from_i64(value: i64) -> u64 {
+    u64::from_ne_bytes(value.to_ne_bytes())
+}
+
+// Schema changes, applied in order. Number of applied migrations is kept in
+// SQLite `user_version` pragma. Never edit existing entries - append new ones.
+static MIGRATIONS: &[&str] = &[
+    r"
+    CREATE TABLE IF NOT EXISTS question_stats
+    (
+        question_id TEXT NOT NULL,
+        choice TEXT NOT NULL,
+        picks INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (question_id, choice)
+    )
+    ",
+    r"
+    CREATE TABLE IF NOT EXISTS leaderboard
+    (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        nickname TEXT NOT NULL,
+        answered_good_q_without_canceled INTEGER NOT NULL,
+        answered_good_q_with_canceled INTEGER NOT NULL,
+        place INTEGER NOT NULL,
+        created_at INTEGER NOT NULL DEFAULT (unixepoch()),
+        hidden INTEGER NOT NULL DEFAULT 0
+    )
+    ",
+    r"
+    CREATE TABLE IF NOT EXISTS attempts
+    (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        player_id TEXT NOT NULL,
+        finished_at INTEGER NOT NULL DEFAULT (unixepoch()),
+        answered_good_q INTEGER NOT NULL,
+        answered_bad_q INTEGER NOT NULL,
+        total_q INTEGER NOT NULL,
+        answered_good_q_without_canceled INTEGER NOT NULL,
+        answered_good_q_with_canceled INTEGER NOT NULL,
+        place INTEGER NOT NULL,
+        count_canceled INTEGER NOT NULL,
+        responses TEXT NOT NULL
+    )
+    ",
+    "CREATE INDEX IF NOT EXISTS attempts_player_id ON attempts (player_id)",
+    "ALTER TABLE attempts ADD COLUMN time_limit TEXT",
+    "ALTER TABLE leaderboard ADD COLUMN timed INTEGER NOT NULL DEFAULT 0",
+    "ALTER TABLE attempts ADD COLUMN shuffle_seed INTEGER",
+    r"
+    CREATE TABLE IF NOT EXISTS accounts
+    (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        password_hash TEXT NOT NULL,
+        player_id TEXT NOT NULL,
+        progress TEXT NOT NULL DEFAULT '{}',
+        created_at INTEGER NOT NULL DEFAULT (unixepoch())
+    )
+    ",
+    r"
+    CREATE TABLE IF NOT EXISTS results
+    (
+        id TEXT PRIMARY KEY,
+        snapshot TEXT NOT NULL,
+        created_at INTEGER NOT NULL DEFAULT (unixepoch())
+    )
+    ",
+    r"
+    CREATE TABLE IF NOT EXISTS responses
+    (
+        player_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        question_id TEXT NOT NULL,
+        answer TEXT NOT NULL,
+        updated_at INTEGER NOT NULL DEFAULT (unixepoch()),
+        PRIMARY KEY (player_id, kind, question_id)
+    )
+    ",
+    r"
+    CREATE TABLE IF NOT EXISTS attempt_starts
+    (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        player_id TEXT NOT NULL,
+        started_at INTEGER NOT NULL DEFAULT (unixepoch())
+    )
+    ",
+    r"
+    CREATE TABLE IF NOT EXISTS events
+    (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        visitor_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        part_id TEXT,
+        question_id TEXT,
+        created_at INTEGER NOT NULL DEFAULT (unixepoch())
+    )
+    ",
+    "CREATE INDEX IF NOT EXISTS events_visitor_id ON events (visitor_id)",
+    "CREATE INDEX IF NOT EXISTS events_created_at ON events (created_at)",
+    r"
+    CREATE TABLE IF NOT EXISTS players
+    (
+        player_id TEXT PRIMARY KEY,
+        schema_version INTEGER NOT NULL,
+        content_hash TEXT NOT NULL,
+        seen_at INTEGER NOT NULL DEFAULT (unixepoch())
+    )
+    ",
+    // Rows saved before `players` table existed are checked on next read
+    r"
+    INSERT OR IGNORE INTO players (player_id, schema_version, content_hash, seen_at)
+    SELECT player_id, 0, '', MAX(seen_at) FROM (
+        SELECT player_id, updated_at AS seen_at FROM responses
+        UNION ALL
+        SELECT player_id, started_at FROM attempt_starts
+    )
+    GROUP BY player_id
+    ",
+    "CREATE INDEX IF NOT EXISTS attempt_starts_player_id ON attempt_starts (player_id)",
+    r"
+    CREATE TABLE IF NOT EXISTS counters
+    (
+        name TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    )
+    ",
+    r"
+    CREATE TABLE IF NOT EXISTS finished_attempts
+    (
+        player_id TEXT NOT NULL,
+        attempt_id TEXT NOT NULL,
+        finished_at INTEGER NOT NULL DEFAULT (unixepoch()),
+        PRIMARY KEY (player_id, attempt_id)
+    )
+    ",
+];
+
+/// Last seen time of players is updated at most this often
+const PLAYER_SEEN_RESOLUTION_SECS: i64 = 24 * 60 * 60;
+/// Players whose answers were last read before this many seconds ago, and
+/// who have no account
+const INACTIVE_PLAYERS: &str = r"
+    SELECT player_id FROM players
+    WHERE seen_at < unixepoch() - ? AND player_id NOT IN (SELECT player_id FROM accounts)
+";
+
+pub(crate) async fn migrate(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let (applied,): (i64,) = sqlx::query_as("PRAGMA user_version")
+        .fetch_one(pool)
+        .await?;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(to_usize(applied)) {
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration).execute(&mut *tx).await?;
+        sqlx::query(&format!("PRAGMA user_version = {}", version + 1))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// Add answers from a finished attempt to site-wide statistics.
+/// Only choices are stored - nothing that could identify the player.
+pub(crate) async fn record_question_stats(
+    pool: &SqlitePool,
+    test_responses: &UserResponseData,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for (question_id, response) in test_responses {
+        sqlx::query(
+            r"
+            INSERT INTO question_stats (question_id, choice, picks) VALUES (?, ?, 1)
+            ON CONFLICT (question_id, choice) DO UPDATE SET picks = picks + 1
+            ",
+        )
+        .bind(question_id)
+        .bind(response.user_answer.to_string())
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+pub(crate) async fn get_question_stats(
+    pool: &SqlitePool,
+) -> Result<Vec<QuestionStatsRow>, sqlx::Error> {
+    sqlx::query_as("SELECT question_id, choice, picks FROM question_stats")
+        .fetch_all(pool)
+        .await
+}
+
+/// Note that player started a new attempt, unless they already have test
+/// answers. Has to be called before the first answer is saved.
+pub(crate) async fn record_attempt_start(
+    pool: &SqlitePool,
+    player_id: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r"
+        INSERT INTO attempt_starts (player_id)
+        SELECT ? WHERE NOT EXISTS (SELECT 1 FROM responses WHERE player_id = ? AND kind = ?)
+        ",
+    )
+    .bind(player_id)
+    .bind(player_id)
+    .bind(ResponseKind::Test.as_str())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn get_attempt_summary(pool: &SqlitePool) -> Result<AttemptSummary, sqlx::Error> {
+    // Starts of pruned players are only kept as a count
+    let (started,): (i64,) = sqlx::query_as(
+        r"
+        SELECT (SELECT COUNT(*) FROM attempt_starts)
+            + COALESCE((SELECT value FROM counters WHERE name = 'pruned_attempt_starts'), 0)
+        ",
+    )
+    .fetch_one(pool)
+    .await?;
+    let (finished, avg_without_canceled, avg_with_canceled): (i64, Option<f64>, Option<f64>) =
+        sqlx::query_as(
+            r"
+            SELECT COUNT(*), AVG(answered_good_q_without_canceled),
+                   AVG(answered_good_q_with_canceled)
+            FROM attempts
+            ",
+        )
+        .fetch_one(pool)
+        .await?;
+    Ok(AttemptSummary {
+        started: to_usize(started),
+        finished: to_usize(finished),
+        avg_good_q_without_canceled: avg_without_canceled.unwrap_or_default(),
+        avg_good_q_with_canceled: avg_with_canceled.unwrap_or_default(),
+    })
+}
+
+/// Number of finished attempts for each place, best place first
+pub(crate) async fn get_place_counts(
+    pool: &SqlitePool,
+) -> Result<Vec<(usize, usize)>, sqlx::Error> {
+    let rows: Vec<(i64, i64)> =
+        sqlx::query_as("SELECT place, COUNT(*) FROM attempts GROUP BY place ORDER BY place")
+            .fetch_all(pool)
+            .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(place, attempts)| (to_usize(place), to_usize(attempts)))
+        .collect())
+}
+
+pub(crate) async fn insert_leaderboard_entry(
+    pool: &SqlitePool,
+    nickname: &str,
+    answered_good_q_without_canceled: usize,
+    answered_good_q_with_canceled: usize,
+    place: usize,
+    timed: bool,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
+        r"
+        INSERT INTO leaderboard
+            (nickname, answered_good_q_without_canceled, answered_good_q_with_canceled, place,
+             timed)
+        VALUES (?, ?, ?, ?, ?)
+        ",
+    )
+    .bind(nickname)
+    .bind(to_i64(answered_good_q_without_canceled))
+    .bind(to_i64(answered_good_q_with_canceled))
+    .bind(to_i64(place))
+    .bind(timed)
+    .execute(pool)
+    .await?;
+    Ok(result.last_insert_rowid())
+}
+
+type LeaderboardRow = (i64, String, i64, i64, i64, i64, bool, bool);
+
+/// Leaderboard entries shown on one page
+pub(crate) const LEADERBOARD_PAGE_SIZE: usize = 50;
+
+/// One page (counted from 0) of leaderboard entries from given period, best
+/// first, and whether there are more pages. Timed attempts are ranked
+/// separately, unless `timed` is `None`. Hidden entries are only returned
+/// for admin view.
+pub(crate) async fn get_leaderboard(
+    pool: &SqlitePool,
+    period: LeaderboardPeriod,
+    count_canceled: bool,
+    timed: Option<bool>,
+    include_hidden: bool,
+    page: usize,
+) -> Result<(Vec<LeaderboardEntry>, bool), sqlx::Error> {
+    let since = match period {
+        LeaderboardPeriod::AllTime => "0",
+        LeaderboardPeriod::Month => "unixepoch('now', '-1 month')",
+        LeaderboardPeriod::Week => "unixepoch('now', '-7 days')",
+    };
+    let hidden = if include_hidden { "" } else { "AND hidden = 0" };
+    let timed = match timed {
+        Some(true) => "AND timed = 1",
+        Some(false) => "AND timed = 0",
+        None => "",
+    };
+    let score = if count_canceled {
+        "answered_good_q_with_canceled"
+    } else {
+        "answered_good_q_without_canceled"
+    };
+    let query = format!(
+        r"
+        SELECT id, nickname, answered_good_q_without_canceled, answered_good_q_with_canceled,
+               place, created_at, hidden, timed
+        FROM leaderboard
+        WHERE created_at >= {since} {hidden} {timed}
+        ORDER BY {score} DESC, created_at ASC, id ASC
+        LIMIT ? OFFSET ?
+        "
+    );
+    // One row more than fits on the page tells if there's a next one
+    let mut rows: Vec<LeaderboardRow> = sqlx::query_as(&query)
+        .bind(to_i64(LEADERBOARD_PAGE_SIZE + 1))
+        .bind(to_i64(page.saturating_mul(LEADERBOARD_PAGE_SIZE)))
+        .fetch_all(pool)
+        .await?;
+    let has_more = rows.len() > LEADERBOARD_PAGE_SIZE;
+    rows.truncate(LEADERBOARD_PAGE_SIZE);
+    Ok((leaderboard_entries_from_rows(rows), has_more))
+}
+
+fn leaderboard_entries_from_rows(rows: Vec<LeaderboardRow>) -> Vec<LeaderboardEntry> {
+    rows.into_iter()
+        .map(
+            |(id, nickname, without_canceled, with_canceled, place, created_at, hidden, timed)| {
+                LeaderboardEntry {
+                    id,
+                    nickname,
+                    answered_good_q_without_canceled: to_usize(without_canceled),
+                    answered_good_q_with_canceled: to_usize(with_canceled),
+                    place: to_usize(place),
+                    created_at,
+                    hidden,
+                    timed,
+                }
+            },
+        )
+        .collect()
+}
+
+/// Latest leaderboard entries, hidden ones included
+pub(crate) async fn get_recent_leaderboard_entries(
+    pool: &SqlitePool,
+    limit: usize,
+) -> Result<Vec<LeaderboardEntry>, sqlx::Error> {
+    let rows: Vec<LeaderboardRow> = sqlx::query_as(
+        r"
+        SELECT id, nickname, answered_good_q_without_canceled, answered_good_q_with_canceled,
+               place, created_at, hidden, timed
+        FROM leaderboard
+        ORDER BY id DESC
+        LIMIT ?
+        ",
+    )
+    .bind(to_i64(limit))
+    .fetch_all(pool)
+    .await?;
+    Ok(leaderboard_entries_from_rows(rows))
+}
+
+pub(crate) async fn set_leaderboard_entry_hidden(
+    pool: &SqlitePool,
+    id: i64,
+    hidden: bool,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE leaderboard SET hidden = ? WHERE id = ?")
+        .bind(hidden)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Mark attempt as finished. Returns `false` if it already was, so when
+/// several requests finish the same attempt at once, only one archives it.
+pub(crate) async fn claim_finished_attempt(
+    pool: &SqlitePool,
+    player_id: &str,
+    attempt_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT OR IGNORE INTO finished_attempts (player_id, attempt_id) VALUES (?, ?)",
+    )
+    .bind(player_id)
+    .bind(attempt_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub(crate) async fn insert_attempt(
+    pool: &SqlitePool,
+    player_id: &str,
+    totals: &TestStateMainPageTotals,
+    count_canceled: bool,
+    test_responses: &UserResponseData,
+    time_limit: Option<&TimeLimit>,
+    shuffle_seed: Option<u64>,
+) -> Result<i64, sqlx::Error> {
+    let responses =
+        serde_json::to_string(test_responses).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    let time_limit = time_limit
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    let result = sqlx::query(
+        r"
+        INSERT INTO attempts
+            (player_id, answered_good_q, answered_bad_q, total_q,
+             answered_good_q_without_canceled, answered_good_q_with_canceled, place,
+             count_canceled, responses, time_limit, shuffle_seed)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ",
+    )
+    .bind(player_id)
+    .bind(to_i64(totals.answered_good_q))
+    .bind(to_i64(totals.answered_bad_q))
+    .bind(to_i64(totals.total_q))
+    .bind(to_i64(totals.answered_good_q_without_canceled))
+    .bind(to_i64(totals.answered_good_q_with_canceled))
+    .bind(to_i64(totals.place))
+    .bind(count_canceled)
+    .bind(responses)
+    .bind(time_limit)
+    .bind(shuffle_seed.map(seed_to_i64))
+    .execute(pool)
+    .await?;
+    Ok(result.last_insert_rowid())
+}
+
+type AttemptRow = (
+    i64,
+    String,
+    i64,
+    i64,
+    i64,
+    i64,
+    i64,
+    i64,
+    bool,
+    String,
+    Option<String>,
+    Option<i64>,
+);
+
+const ATTEMPT_COLUMNS: &str = r"
+    id, strftime('%Y-%m-%d %H:%M', finished_at, 'unixepoch'), answered_good_q, answered_bad_q,
+    total_q, answered_good_q_without_canceled, answered_good_q_with_canceled, place,
+    count_canceled, responses, time_limit, shuffle_seed
+";
+
+fn attempt_from_row(row: AttemptRow) -> Attempt {
+    let (
+        id,
+        finished_at,
+        answered_good_q,
+        answered_bad_q,
+        total_q,
+        answered_good_q_without_canceled,
+        answered_good_q_with_canceled,
+        place,
+        count_canceled,
+        responses,
+        time_limit,
+        shuffle_seed,
+    ) = row;
+    Attempt {
+        id,
+        finished_at,
+        answered_good_q: to_usize(answered_good_q),
+        answered_bad_q: to_usize(answered_bad_q),
+        total_q: to_usize(total_q),
+        answered_good_q_without_canceled: to_usize(answered_good_q_without_canceled),
+        answered_good_q_with_canceled: to_usize(answered_good_q_with_canceled),
+        place: to_usize(place),
+        count_canceled,
+        responses: serde_json::from_str(&responses).unwrap_or_default(),
+        time_limit: time_limit.and_then(|t| serde_json::from_str(&t).ok()),
+        shuffle_seed: shuffle_seed.map(seed_from_i64),
+    }
+}
+
+/// All finished attempts of a player, oldest first
+pub(crate) async fn get_attempts(
+    pool: &SqlitePool,
+    player_id: &str,
+) -> Result<Vec<Attempt>, sqlx::Error> {
+    let query =
+        format!("SELECT {ATTEMPT_COLUMNS} FROM attempts WHERE player_id = ? ORDER BY id ASC");
+    let rows: Vec<AttemptRow> = sqlx::query_as(&query)
+        .bind(player_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(attempt_from_row).collect())
+}
+
+pub(crate) async fn get_attempt(
+    pool: &SqlitePool,
+    player_id: &str,
+    id: i64,
+) -> Result<Option<Attempt>, sqlx::Error> {
+    let query = format!("SELECT {ATTEMPT_COLUMNS} FROM attempts WHERE player_id = ? AND id = ?");
+    let row: Option<AttemptRow> = sqlx::query_as(&query)
+        .bind(player_id)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(attempt_from_row))
+}
+
+/// Returns `None` when username is already taken
+pub(crate) async fn insert_account(
+    pool: &SqlitePool,
+    username: &str,
+    password_hash: &str,
+    player_id: &str,
+    progress: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let result = sqlx::query(
+        r"
+        INSERT INTO accounts (username, password_hash, player_id, progress)
+        VALUES (?, ?, ?, ?)
+        ",
+    )
+    .bind(username)
+    .bind(password_hash)
+    .bind(player_id)
+    .bind(progress)
+    .execute(pool)
+    .await;
+    match result {
+        Ok(result) => Ok(Some(result.last_insert_rowid())),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Account ID, username, password hash, player ID and saved progress
+pub(crate) type AccountRow = (i64, String, String, String, String);
+
+pub(crate) async fn get_account(
+    pool: &SqlitePool,
+    username: &str,
+) -> Result<Option<AccountRow>, sqlx::Error> {
+    sqlx::query_as(
+        r"
+        SELECT id, username, password_hash, player_id, progress
+        FROM accounts
+        WHERE username = ?
+        ",
+    )
+    .bind(username)
+    .fetch_optional(pool)
+    .await
+}
+
+pub(crate) async fn get_account_progress(
+    pool: &SqlitePool,
+    id: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT progress FROM accounts WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|(progress,)| progress))
+}
+
+pub(crate) async fn set_account_progress(
+    pool: &SqlitePool,
+    id: i64,
+    progress: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE accounts SET progress = ? WHERE id = ?")
+        .bind(progress)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Move archived attempts of anonymous player to an account
+pub(crate) async fn reassign_attempts(
+    pool: &SqlitePool,
+    from_player_id: &str,
+    to_player_id: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("UPDATE attempts SET player_id = ? WHERE player_id = ?")
+        .bind(to_player_id)
+        .bind(from_player_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Results are never changed once saved
+pub(crate) async fn insert_result(
+    pool: &SqlitePool,
+    id: &str,
+    snapshot: &ResultSnapshot,
+) -> Result<(), sqlx::Error> {
+    let snapshot = serde_json::to_string(snapshot).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    sqlx::query("INSERT INTO results (id, snapshot) VALUES (?, ?)")
+        .bind(id)
+        .bind(snapshot)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn get_result(
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<Option<ResultSnapshot>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT snapshot FROM results WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    row.map(|(snapshot,)| {
+        serde_json::from_str(&snapshot).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+    })
+    .transpose()
+}
+
+pub(crate) async fn get_responses(
+    pool: &SqlitePool,
+    player_id: &str,
+    kind: ResponseKind,
+) -> Result<Vec<ResponseRow>, sqlx::Error> {
+    sqlx::query_as("SELECT question_id, answer FROM responses WHERE player_id = ? AND kind = ?")
+        .bind(player_id)
+        .bind(kind.as_str())
+        .fetch_all(pool)
+        .await
+}
+
+async fn insert_responses(
+    pool: &SqlitePool,
+    player_id: &str,
+    kind: ResponseKind,
+    answers: Vec<(String, char)>,
+    on_conflict: &str,
+) -> Result<(), sqlx::Error> {
+    let query = format!(
+        r"
+        INSERT INTO responses (player_id, kind, question_id, answer) VALUES (?, ?, ?, ?)
+        ON CONFLICT (player_id, kind, question_id) {on_conflict}
+        "
+    );
+    let mut tx = pool.begin().await?;
+    for (question_id, answer) in answers {
+        sqlx::query(&query)
+            .bind(player_id)
+            .bind(kind.as_str())
+            .bind(question_id)
+            .bind(answer.to_string())
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// Save answers, replacing earlier answers to the same questions. Each
+/// answer is its own row, so concurrent saves of different questions
+/// don't overwrite each other.
+pub(crate) async fn upsert_responses(
+    pool: &SqlitePool,
+    player_id: &str,
+    kind: ResponseKind,
+    responses: &UserResponseData,
+) -> Result<(), sqlx::Error> {
+    let answers = responses
+        .iter()
+        .map(|(id, r)| (id.clone(), r.user_answer))
+        .collect();
+    insert_responses(
+        pool,
+        player_id,
+        kind,
+        answers,
+        "DO UPDATE SET answer = excluded.answer, updated_at = unixepoch()",
+    )
+    .await
+}
+
+/// Save answers only for questions that have no answer yet
+pub(crate) async fn insert_missing_responses(
+    pool: &SqlitePool,
+    player_id: &str,
+    kind: ResponseKind,
+    answers: &BTreeMap<String, char>,
+) -> Result<(), sqlx::Error> {
+    let answers = answers.iter().map(|(id, a)| (id.clone(), *a)).collect();
+    insert_responses(pool, player_id, kind, answers, "DO NOTHING").await
+}
+
+pub(crate) async fn clear_responses(
+    pool: &SqlitePool,
+    player_id: &str,
+    kind: ResponseKind,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM responses WHERE player_id = ? AND kind = ?")
+        .bind(player_id)
+        .bind(kind.as_str())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn delete_response(
+    pool: &SqlitePool,
+    player_id: &str,
+    kind: ResponseKind,
+    question_id: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM responses WHERE player_id = ? AND kind = ? AND question_id = ?")
+        .bind(player_id)
+        .bind(kind.as_str())
+        .bind(question_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Replace all answers of given kind at once
+pub(crate) async fn replace_responses(
+    pool: &SqlitePool,
+    player_id: &str,
+    kind: ResponseKind,
+    responses: &UserResponseData,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM responses WHERE player_id = ? AND kind = ?")
+        .bind(player_id)
+        .bind(kind.as_str())
+        .execute(&mut *tx)
+        .await?;
+    for (question_id, response) in responses {
+        sqlx::query(
+            "INSERT INTO responses (player_id, kind, question_id, answer) VALUES (?, ?, ?, ?)",
+        )
+        .bind(player_id)
+        .bind(kind.as_str())
+        .bind(question_id)
+        .bind(response.user_answer.to_string())
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Carry answers of anonymous player over to an account, for questions not
+/// answered on the account yet
+pub(crate) async fn merge_responses(
+    pool: &SqlitePool,
+    from_player_id: &str,
+    to_player_id: &str,
+    kind: ResponseKind,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r"
+        INSERT INTO responses (player_id, kind, question_id, answer, updated_at)
+        SELECT ?, kind, question_id, answer, updated_at
+        FROM responses
+        WHERE player_id = ? AND kind = ?
+        ON CONFLICT (player_id, kind, question_id) DO NOTHING
+        ",
+    )
+    .bind(to_player_id)
+    .bind(from_player_id)
+    .bind(kind.as_str())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn delete_player_responses(
+    pool: &SqlitePool,
+    player_id: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM responses WHERE player_id = ?")
+        .bind(player_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Note that player's answers are in use, and tell whether they were saved
+/// with another layout of rows or for other test content, see
+/// `giga_test::content_hash`. Last seen time is bumped at most once a day,
+/// so reading answers doesn't write on every request.
+pub(crate) async fn touch_player(
+    pool: &SqlitePool,
+    player_id: &str,
+    content_hash: &str,
+) -> Result<bool, sqlx::Error> {
+    let row: Option<(i64, String, i64)> = sqlx::query_as(
+        "SELECT schema_version, content_hash, unixepoch() - seen_at FROM players WHERE player_id = ?",
+    )
+    .bind(player_id)
+    .fetch_optional(pool)
+    .await?;
+    let outdated = row.as_ref().is_some_and(|(schema_version, hash, _)| {
+        *schema_version != RESPONSES_SCHEMA_VERSION || hash != content_hash
+    });
+    let stale = row
+        .as_ref()
+        .is_none_or(|(_, _, seen_ago)| *seen_ago >= PLAYER_SEEN_RESOLUTION_SECS);
+    if outdated || stale {
+        sqlx::query(
+            r"
+            INSERT INTO players (player_id, schema_version, content_hash) VALUES (?, ?, ?)
+            ON CONFLICT (player_id) DO UPDATE SET
+                schema_version = excluded.schema_version,
+                content_hash = excluded.content_hash,
+                seen_at = excluded.seen_at
+            ",
+        )
+        .bind(player_id)
+        .bind(RESPONSES_SCHEMA_VERSION)
+        .bind(content_hash)
+        .execute(pool)
+        .await?;
+    }
+    Ok(outdated)
+}
+
+/// Delete answers of anonymous players not seen for `inactive_days` - by
+/// then their sessions have expired, so nobody can read them anymore.
+/// Their attempt starts are only kept as a count, for statistics. Returns
+/// number of deleted players.
+pub(crate) async fn prune_inactive_players(
+    pool: &SqlitePool,
+    inactive_days: u64,
+) -> Result<u64, sqlx::Error> {
+    let inactive_secs = i64::try_from(inactive_days * 24 * 60 * 60).unwrap_or(i64::MAX);
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!(
+        r"
+        INSERT INTO counters (name, value)
+        SELECT 'pruned_attempt_starts', COUNT(*) FROM attempt_starts
+        WHERE player_id IN ({INACTIVE_PLAYERS})
+        ON CONFLICT (name) DO UPDATE SET value = value + excluded.value
+        "
+    ))
+    .bind(inactive_secs)
+    .execute(&mut *tx)
+    .await?;
+    for table in ["attempt_starts", "finished_attempts", "responses"] {
+        sqlx::query(&format!(
+            "DELETE FROM {table} WHERE player_id IN ({INACTIVE_PLAYERS})"
+        ))
+        .bind(inactive_secs)
+        .execute(&mut *tx)
+        .await?;
+    }
+    let deleted = sqlx::query(&format!(
+        "DELETE FROM players WHERE player_id IN ({INACTIVE_PLAYERS})"
+    ))
+    .bind(inactive_secs)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+pub(crate) async fn insert_events(
+    pool: &SqlitePool,
+    visitor_id: &str,
+    events: &[Event],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for event in events {
+        sqlx::query(
+            "INSERT INTO events (visitor_id, kind, part_id, question_id) VALUES (?, ?, ?, ?)",
+        )
+        .bind(visitor_id)
+        .bind(event.kind.as_str())
+        .bind(&event.part_id)
+        .bind(&event.question_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Delete events older than retention period, return how many were deleted
+pub(crate) async fn delete_old_events(pool: &SqlitePool, days: u64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM events WHERE created_at < unixepoch() - ?")
+        .bind(i64::try_from(days.saturating_mul(86_400)).unwrap_or(i64::MAX))
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub(crate) async fn get_analytics_summary(
+    pool: &SqlitePool,
+) -> Result<AnalyticsSummary, sqlx::Error> {
+    let (visitors, new_tests, submits): (i64, Option<i64>, Option<i64>) = sqlx::query_as(
+        r"
+        SELECT COUNT(DISTINCT visitor_id), SUM(kind = ?), SUM(kind = ?)
+        FROM events
+        ",
+    )
+    .bind(EventKind::NewTest.as_str())
+    .bind(EventKind::Submit.as_str())
+    .fetch_one(pool)
+    .await?;
+    Ok(AnalyticsSummary {
+        visitors: to_usize(visitors),
+        new_tests: to_usize(new_tests.unwrap_or_default()),
+        submits: to_usize(submits.unwrap_or_default()),
+    })
+}
+
+/// Part views, and total seconds until visitor's next event. Views without
+/// a next event are not timed, and time of each view is capped at
+/// `max_seconds`, so tabs left open overnight don't skew the average.
+pub(crate) async fn get_part_view_times(
+    pool: &SqlitePool,
+    max_seconds: u64,
+) -> Result<Vec<PartViewTimesRow>, sqlx::Error> {
+    sqlx::query_as(
+        r"
+        SELECT part_id, COUNT(*), COUNT(next_at), COALESCE(SUM(MIN(next_at - created_at, ?)), 0)
+        FROM (
+            SELECT part_id, kind, created_at,
+                   LEAD(created_at) OVER (PARTITION BY visitor_id ORDER BY id) AS next_at
+            FROM events
+        )
+        WHERE kind = ?
+        GROUP BY part_id
+        ",
+    )
+    .bind(i64::try_from(max_seconds).unwrap_or(i64::MAX))
+    .bind(EventKind::PartView.as_str())
+    .fetch_all(pool)
+    .await
+}
+
+/// Part ID, first answers and changed answers in that part
+pub(crate) async fn get_part_answer_counts(
+    pool: &SqlitePool,
+) -> Result<Vec<PartCountsRow>, sqlx::Error> {
+    sqlx::query_as(
+        r"
+        SELECT part_id, SUM(kind = ?), SUM(kind = ?)
+        FROM events
+        WHERE kind IN (?, ?)
+        GROUP BY part_id
+        ",
+    )
+    .bind(EventKind::Answer.as_str())
+    .bind(EventKind::AnswerChange.as_str())
+    .bind(EventKind::Answer.as_str())
+    .bind(EventKind::AnswerChange.as_str())
+    .fetch_all(pool)
+    .await
+}
+
+/// Part ID and number of visitors who gave up there - they answered at
+/// least one question, but their last event is not a submission and is
+/// older than `inactive_seconds`. Part is taken from their last event that
+/// has one.
+pub(crate) async fn get_part_give_ups(
+    pool: &SqlitePool,
+    inactive_seconds: u64,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    sqlx::query_as(
+        r"
+        WITH last_event AS (
+            SELECT visitor_id, MAX(id) AS id FROM events GROUP BY visitor_id
+        ), last_part_event AS (
+            SELECT visitor_id, MAX(id) AS id FROM events
+            WHERE part_id IS NOT NULL
+            GROUP BY visitor_id
+        )
+        SELECT part_event.part_id, COUNT(*)
+        FROM last_event
+        JOIN events AS event ON event.id = last_event.id
+        JOIN last_part_event ON last_part_event.visitor_id = last_event.visitor_id
+        JOIN events AS part_event ON part_event.id = last_part_event.id
+        WHERE event.kind != ?
+          AND event.created_at < unixepoch() - ?
+          AND EXISTS (
+              SELECT 1 FROM events AS answer
+              WHERE answer.visitor_id = last_event.visitor_id AND answer.kind IN (?, ?)
+          )
+        GROUP BY part_event.part_id
+        ",
+    )
+    .bind(EventKind::Submit.as_str())
+    .bind(i64::try_from(inactive_seconds).unwrap_or(i64::MAX))
+    .bind(EventKind::Answer.as_str())
+    .bind(EventKind::AnswerChange.as_str())
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::responses;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        migrate(&pool).await.unwrap();
+        pool
+    }
+
+    async fn answers(pool: &SqlitePool, player_id: &str, kind: ResponseKind) -> Vec<ResponseRow> {
+        let mut rows = get_responses(pool, player_id, kind).await.unwrap();
+        rows.sort();
+        rows
+    }
+
+    fn rows(answers: &[(&str, &str)]) -> Vec<ResponseRow> {
+        answers
+            .iter()
+            .map(|(id, answer)| ((*id).to_string(), (*answer).to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_upsert_responses_keeps_other_questions() {
+        let pool = test_pool().await;
+        let kind = ResponseKind::Test;
+        // Two tabs saving different questions
+        upsert_responses(&pool, "p", kind, &responses(&[("q1_1_0", 'A')]))
+            .await
+            .unwrap();
+        upsert_responses(&pool, "p", kind, &responses(&[("q1_1_1", 'B')]))
+            .await
+            .unwrap();
+        upsert_responses(&pool, "p", kind, &responses(&[("q1_1_0", 'C')]))
+            .await
+            .unwrap();
+        assert_eq!(
+            answers(&pool, "p", kind).await,
+            rows(&[("q1_1_0", "C"), ("q1_1_1", "B")])
+        );
+        assert!(answers(&pool, "p", ResponseKind::Practice).await.is_empty());
+        delete_response(&pool, "p", kind, "q1_1_1").await.unwrap();
+        assert_eq!(answers(&pool, "p", kind).await, rows(&[("q1_1_0", "C")]));
+
+        replace_responses(&pool, "p", kind, &responses(&[("q2_1_0", 'D')]))
+            .await
+            .unwrap();
+        assert_eq!(answers(&pool, "p", kind).await, rows(&[("q2_1_0", "D")]));
+        clear_responses(&pool, "p", kind).await.unwrap();
+        assert!(answers(&pool, "p", kind).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_missing_and_merged_responses_do_not_overwrite() {
+        let pool = test_pool().await;
+        let kind = ResponseKind::Practice;
+        upsert_responses(&pool, "account", kind, &responses(&[("q1_1_0", 'A')]))
+            .await
+            .unwrap();
+        let legacy = BTreeMap::from([("q1_1_0".to_string(), 'B'), ("q1_1_1".to_string(), 'B')]);
+        insert_missing_responses(&pool, "account", kind, &legacy)
+            .await
+            .unwrap();
+        assert_eq!(
+            answers(&pool, "account", kind).await,
+            rows(&[("q1_1_0", "A"), ("q1_1_1", "B")])
+        );
+
+        upsert_responses(
+            &pool,
+            "anonymous",
+            kind,
+            &responses(&[("q1_1_1", 'C'), ("q1_1_2", 'C')]),
+        )
+        .await
+        .unwrap();
+        merge_responses(&pool, "anonymous", "account", kind)
+            .await
+            .unwrap();
+        delete_player_responses(&pool, "anonymous").await.unwrap();
+        assert_eq!(
+            answers(&pool, "account", kind).await,
+            rows(&[("q1_1_0", "A"), ("q1_1_1", "B"), ("q1_1_2", "C")])
+        );
+        assert!(answers(&pool, "anonymous", kind).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_leaderboard_pages() {
+        let pool = test_pool().await;
+        for score in 0..=LEADERBOARD_PAGE_SIZE {
+            insert_leaderboard_entry(&pool, "player", score, score, 1, false)
+                .await
+                .unwrap();
+        }
+        let period = LeaderboardPeriod::AllTime;
+        let (first, has_more) = get_leaderboard(&pool, period, false, None, false, 0)
+            .await
+            .unwrap();
+        assert_eq!(first.len(), LEADERBOARD_PAGE_SIZE);
+        assert_eq!(
+            first[0].answered_good_q_without_canceled,
+            LEADERBOARD_PAGE_SIZE
+        );
+        assert!(has_more);
+        let (second, has_more) = get_leaderboard(&pool, period, false, None, false, 1)
+            .await
+            .unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].answered_good_q_without_canceled, 0);
+        assert!(!has_more);
+
+        // Latest first, regardless of score
+        insert_leaderboard_entry(&pool, "latest", 0, 0, 1, false)
+            .await
+            .unwrap();
+        let recent = get_recent_leaderboard_entries(&pool, 3).await.unwrap();
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0].nickname, "latest");
+        assert_eq!(
+            recent[1].answered_good_q_without_canceled,
+            LEADERBOARD_PAGE_SIZE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_result_snapshot() {
+        let pool = test_pool().await;
+        // Snapshot saved before it had its own types and version
+        let snapshot = r#"{"count_canceled":false,"totals":{"answered_good_q":5,"answered_bad_q":1,"answered_total_q":6,"total_q":500,"answered_good_q_without_canceled":5,"answered_good_q_with_canceled":5,"place":300,"place_bucket":"loser"},"parts":[{"test_id":"1","pe_id":"23","pe_date":"03/2000","answered_q":6,"total_q":100,"answered_good_q":5,"answered_bad_q":1,"answered_good_regular_q":5,"answered_good_canceled_q":0,"seconds_to_unlock":null,"seconds_to_deadline":null}]}"#;
+        sqlx::query("INSERT INTO results (id, snapshot) VALUES ('old', ?)")
+            .bind(snapshot)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let snapshot = get_result(&pool, "old").await.unwrap().unwrap();
+        assert_eq!(snapshot.version, 0);
+        assert_eq!(snapshot.totals.place, 300);
+        assert_eq!(snapshot.parts[0].answered_good_q, 5);
+    }
+
+    #[test]
+    fn test_seed_round_trip() {
+        for seed in [0, 42, i64::MAX as u64 + 1, u64::MAX] {
+            assert_eq!(seed_from_i64(seed_to_i64(seed)), seed);
+        }
+        assert_eq!(seed_to_i64(u64::MAX), -1);
+    }
+
+    #[tokio::test]
+    async fn test_record_attempt_start_once_per_attempt() {
+        let pool = test_pool().await;
+        let kind = ResponseKind::Test;
+        for answer in ['A', 'B'] {
+            record_attempt_start(&pool, "p").await.unwrap();
+            upsert_responses(&pool, "p", kind, &responses(&[("q1_1_0", answer)]))
+                .await
+                .unwrap();
+        }
+        assert_eq!(get_attempt_summary(&pool).await.unwrap().started, 1);
+
+        // New test clears answers, so next answer starts another attempt
+        clear_responses(&pool, "p", kind).await.unwrap();
+        record_attempt_start(&pool, "p").await.unwrap();
+        let summary = get_attempt_summary(&pool).await.unwrap();
+        assert_eq!(summary.started, 2);
+        assert_eq!(summary.finished, 0);
+    }
+
+    #[tokio::test]
+    async fn test_touch_player_tells_outdated_content() {
+        let pool = test_pool().await;
+        assert!(!touch_player(&pool, "p", "hash").await.unwrap());
+        assert!(!touch_player(&pool, "p", "hash").await.unwrap());
+        assert!(touch_player(&pool, "p", "fixed hash").await.unwrap());
+        assert!(!touch_player(&pool, "p", "fixed hash").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_prune_inactive_players() {
+        let pool = test_pool().await;
+        let kind = ResponseKind::Test;
+        insert_account(&pool, "konto", "hash", "account", "{}")
+            .await
+            .unwrap();
+        for player_id in ["gone", "active", "account"] {
+            record_attempt_start(&pool, player_id).await.unwrap();
+            upsert_responses(&pool, player_id, kind, &responses(&[("q1_1_0", 'A')]))
+                .await
+                .unwrap();
+            touch_player(&pool, player_id, "hash").await.unwrap();
+        }
+        sqlx::query("UPDATE players SET seen_at = unixepoch() - 31 * 86400 WHERE player_id != ?")
+            .bind("active")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(prune_inactive_players(&pool, 30).await.unwrap(), 1);
+        assert!(answers(&pool, "gone", kind).await.is_empty());
+        assert_eq!(answers(&pool, "active", kind).await.len(), 1);
+        assert_eq!(answers(&pool, "account", kind).await.len(), 1);
+        assert_eq!(get_attempt_summary(&pool).await.unwrap().started, 3);
+        assert_eq!(prune_inactive_players(&pool, 30).await.unwrap(), 0);
+        assert_eq!(get_attempt_summary(&pool).await.unwrap().started, 3);
+    }
+
+    #[tokio::test]
+    async fn test_claim_finished_attempt_once() {
+        let pool = test_pool().await;
+        assert!(claim_finished_attempt(&pool, "p", "").await.unwrap());
+        assert!(!claim_finished_attempt(&pool, "p", "").await.unwrap());
+        assert!(claim_finished_attempt(&pool, "p", "next").await.unwrap());
+        assert!(claim_finished_attempt(&pool, "q", "").await.unwrap());
+    }
+
+    async fn insert_event_at(
+        pool: &SqlitePool,
+        visitor_id: &str,
+        kind: EventKind,
+        part_id: Option<&str>,
+        seconds_ago: i64,
+    ) {
+        let event = Event {
+            kind,
+            part_id: part_id.map(String::from),
+            question_id: None,
+        };
+        insert_events(pool, visitor_id, &[event]).await.unwrap();
+        sqlx::query(
+            "UPDATE events SET created_at = unixepoch() - ? WHERE id = (SELECT MAX(id) FROM events)",
+        )
+        .bind(seconds_ago)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_event_reports() {
+        let pool = test_pool().await;
+        let day = 86_400;
+        // Finished the test, spending a minute on part 1 and long on part 2
+        insert_event_at(&pool, "a", EventKind::PartView, Some("1"), 2 * day).await;
+        insert_event_at(&pool, "a", EventKind::Answer, Some("1"), 2 * day - 60).await;
+        insert_event_at(&pool, "a", EventKind::PartView, Some("2"), 2 * day - 100).await;
+        insert_event_at(&pool, "a", EventKind::AnswerChange, Some("1"), day).await;
+        insert_event_at(&pool, "a", EventKind::Submit, None, day - 10).await;
+        // Answered in part 1, looked at part 2 and left
+        insert_event_at(&pool, "b", EventKind::PartView, Some("1"), 2 * day).await;
+        insert_event_at(&pool, "b", EventKind::Answer, Some("1"), 2 * day - 20).await;
+        insert_event_at(&pool, "b", EventKind::PartView, Some("2"), 2 * day - 30).await;
+        // Only looked, didn't answer anything
+        insert_event_at(&pool, "c", EventKind::PartView, Some("1"), 2 * day).await;
+
+        let summary = get_analytics_summary(&pool).await.unwrap();
+        assert_eq!(summary.visitors, 3);
+        assert_eq!(summary.submits, 1);
+
+        let mut view_times = get_part_view_times(&pool, 1800).await.unwrap();
+        view_times.sort();
+        assert_eq!(
+            view_times,
+            [
+                ("1".to_string(), 3, 2, 60 + 20),
+                ("2".to_string(), 2, 1, 1800),
+            ]
+        );
+        assert_eq!(
+            get_part_answer_counts(&pool).await.unwrap(),
+            [("1".to_string(), 2, 1)]
+        );
+        assert_eq!(
+            get_part_give_ups(&pool, 86_400).await.unwrap(),
+            [("2".to_string(), 1)]
+        );
+        assert!(get_part_give_ups(&pool, 3 * 86_400)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(delete_old_events(&pool, 1).await.unwrap(), 7);
+        assert_eq!(get_analytics_summary(&pool).await.unwrap().visitors, 1);
+    }
+}
@@ -1,3 +1,6 @@
+use crate::models::Schedule;
+use crate::sessions::SessionBackendConfig;
+use crate::sqlite::SqliteConfig;
 use std::net::{AddrParseError, SocketAddr};
 use std::num::ParseIntError;
 use std::time::Duration;
@@ -6,11 +9,32 @@ const GIGA_TEST_PORT: &str = "GIGA_TEST_PORT";
 const GIGA_TEST_ADDRESS: &str = "GIGA_TEST_ADDRESS";
 const GIGA_TEST_HTTP_TIMEOUT: &str = "GIGA_TEST_HTTP_TIMEOUT";
 const GIGA_TEST_SQLITE_PATH: &str = "GIGA_TEST_SQLITE_PATH";
+const GIGA_TEST_SQLITE_MAX_CONNECTIONS: &str = "GIGA_TEST_SQLITE_MAX_CONNECTIONS";
+const GIGA_TEST_SQLITE_WAL: &str = "GIGA_TEST_SQLITE_WAL";
+const GIGA_TEST_SQLITE_BUSY_TIMEOUT_MS: &str = "GIGA_TEST_SQLITE_BUSY_TIMEOUT_MS";
+const GIGA_TEST_ADMIN_PASSWORD: &str = "GIGA_TEST_ADMIN_PASSWORD";
+const GIGA_TEST_SCHEDULE: &str = "GIGA_TEST_SCHEDULE";
+const GIGA_TEST_SECRET_KEY: &str = "GIGA_TEST_SECRET_KEY";
+const GIGA_TEST_SESSION_BACKEND: &str = "GIGA_TEST_SESSION_BACKEND";
+const GIGA_TEST_SESSION_POSTGRES_URL: &str = "GIGA_TEST_SESSION_POSTGRES_URL";
+const GIGA_TEST_SESSION_REDIS_URL: &str = "GIGA_TEST_SESSION_REDIS_URL";
+const GIGA_TEST_SESSION_EXPIRY_DAYS: &str = "GIGA_TEST_SESSION_EXPIRY_DAYS";
+const GIGA_TEST_SESSION_CLEANUP_MINUTES: &str = "GIGA_TEST_SESSION_CLEANUP_MINUTES";
+const GIGA_TEST_ANALYTICS_RETENTION_DAYS: &str = "GIGA_TEST_ANALYTICS_RETENTION_DAYS";
+const GIGA_TEST_MAX_BODY_BYTES: &str = "GIGA_TEST_MAX_BODY_BYTES";
+const GIGA_TEST_PUBLIC_URL: &str = "GIGA_TEST_PUBLIC_URL";
 
 pub(crate) const DEFAULT_PORT: usize = 8088;
 pub(crate) const DEFAULT_ADDRESS: &str = "127.0.0.1";
 pub(crate) const DEFAULT_HTTP_TIMEOUT: Duration = Duration::from_secs(5);
 pub(crate) const DEFAULT_SQLITE_PATH: &str = ":memory:";
+pub(crate) const DEFAULT_SQLITE_MAX_CONNECTIONS: u32 = 4;
+pub(crate) const DEFAULT_SQLITE_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+pub(crate) const DEFAULT_SESSION_EXPIRY_DAYS: u64 = 365;
+const MAX_SESSION_EXPIRY_DAYS: u64 = 36500;
+pub(crate) const DEFAULT_SESSION_CLEANUP_MINUTES: u64 = 60;
+const MAX_ANALYTICS_RETENTION_DAYS: u64 = 3650;
+pub(crate) const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -18,8 +42,40 @@ pub enum Error {
     InvalidPort(ParseIntError),
     #[error("failed to parse {GIGA_TEST_HTTP_TIMEOUT}: {0}")]
     HttpTimeout(ParseIntError),
+    #[error("failed to parse {GIGA_TEST_SQLITE_MAX_CONNECTIONS}: {0}")]
+    SqliteMaxConnections(ParseIntError),
+    #[error("{GIGA_TEST_SQLITE_MAX_CONNECTIONS} must be at least 1")]
+    ZeroSqliteConnections,
+    #[error("failed to parse {GIGA_TEST_SQLITE_WAL}: {0}, expected true or false")]
+    SqliteWal(String),
+    #[error("failed to parse {GIGA_TEST_SQLITE_BUSY_TIMEOUT_MS}: {0}")]
+    SqliteBusyTimeout(ParseIntError),
     #[error("failed to parse socket address: {0}")]
     InvalidAddress(AddrParseError),
+    #[error("failed to read {GIGA_TEST_SCHEDULE}: {0}")]
+    ScheduleRead(std::io::Error),
+    #[error("failed to parse {GIGA_TEST_SCHEDULE}: {0}")]
+    ScheduleParse(toml::de::Error),
+    #[error(
+        "unknown {GIGA_TEST_SESSION_BACKEND}: {0}, expected memory, sqlite, postgres or redis"
+    )]
+    UnknownSessionBackend(String),
+    #[error("{0} must be set for selected session backend")]
+    MissingSessionUrl(&'static str),
+    #[error("failed to parse {GIGA_TEST_SESSION_EXPIRY_DAYS}: {0}")]
+    SessionExpiry(ParseIntError),
+    #[error("{GIGA_TEST_SESSION_EXPIRY_DAYS} must be between 1 and {MAX_SESSION_EXPIRY_DAYS}")]
+    SessionExpiryRange,
+    #[error("failed to parse {GIGA_TEST_SESSION_CLEANUP_MINUTES}: {0}")]
+    SessionCleanup(ParseIntError),
+    #[error("failed to parse {GIGA_TEST_ANALYTICS_RETENTION_DAYS}: {0}")]
+    AnalyticsRetention(ParseIntError),
+    #[error("{GIGA_TEST_ANALYTICS_RETENTION_DAYS} must be at most {MAX_ANALYTICS_RETENTION_DAYS}")]
+    AnalyticsRetentionRange,
+    #[error("failed to parse {GIGA_TEST_MAX_BODY_BYTES}: {0}")]
+    MaxBody(ParseIntError),
+    #[error("{GIGA_TEST_MAX_BODY_BYTES} must be at least 1")]
+    ZeroMaxBody,
 }
 
 pub(crate) fn bind_addr() -> Result<SocketAddr, Error> {
@@ -41,8 +97,134 @@ pub(crate) fn http_timeout() -> Result<Duration, Error> {
         .map_err(Error::HttpTimeout)
 }
 
-pub(crate) fn sqlite_pool() -> Result<String, Error> {
-    std::env::var(GIGA_TEST_SQLITE_PATH)
-        .or(Ok(DEFAULT_SQLITE_PATH.to_string()))
-        .map(|s| format!("sqlite:{s}").to_string())
+/// SQLite database and pool settings. In-memory database always uses
+/// a single connection.
+pub(crate) fn sqlite_config() -> Result<SqliteConfig, Error> {
+    let path = std::env::var(GIGA_TEST_SQLITE_PATH).unwrap_or(DEFAULT_SQLITE_PATH.to_string());
+    let max_connections = std::env::var(GIGA_TEST_SQLITE_MAX_CONNECTIONS)
+        .map_or_else(|_| Ok(DEFAULT_SQLITE_MAX_CONNECTIONS), |s| s.parse::<u32>())
+        .map_err(Error::SqliteMaxConnections)?;
+    if max_connections == 0 {
+        return Err(Error::ZeroSqliteConnections);
+    }
+    let wal = match std::env::var(GIGA_TEST_SQLITE_WAL) {
+        Ok(s) => s.parse::<bool>().map_err(|_| Error::SqliteWal(s))?,
+        Err(_) => true,
+    };
+    let busy_timeout = std::env::var(GIGA_TEST_SQLITE_BUSY_TIMEOUT_MS)
+        .map_or_else(
+            |_| Ok(DEFAULT_SQLITE_BUSY_TIMEOUT),
+            |s| s.parse::<u64>().map(Duration::from_millis),
+        )
+        .map_err(Error::SqliteBusyTimeout)?;
+    Ok(SqliteConfig {
+        url: format!("sqlite:{path}"),
+        max_connections,
+        wal,
+        busy_timeout,
+    })
+}
+
+/// Admin pages are disabled when password is not set
+pub(crate) fn admin_password() -> Option<String> {
+    std::env::var(GIGA_TEST_ADMIN_PASSWORD)
+        .ok()
+        .filter(|s| !s.is_empty())
+}
+
+/// Key for signing data handed out to players, like exported progress
+pub(crate) fn secret_key() -> Option<String> {
+    std::env::var(GIGA_TEST_SECRET_KEY)
+        .ok()
+        .filter(|s| !s.is_empty())
+}
+
+/// Address the site is reachable at, e.g. `https://gigatest.example.com`,
+/// for links that have to be absolute
+pub(crate) fn public_url() -> Option<String> {
+    std::env::var(GIGA_TEST_PUBLIC_URL)
+        .ok()
+        .map(|s| s.trim_end_matches('/').to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Release schedule mode is enabled by pointing to schedule file
+pub(crate) fn schedule() -> Result<Option<Schedule>, Error> {
+    let Ok(path) = std::env::var(GIGA_TEST_SCHEDULE) else {
+        return Ok(None);
+    };
+    let content = std::fs::read_to_string(path).map_err(Error::ScheduleRead)?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(Error::ScheduleParse)
+}
+
+/// Where sessions are kept. SQLite uses the same database as everything
+/// else, other backends take their own connection URL.
+pub(crate) fn session_backend() -> Result<SessionBackendConfig, Error> {
+    let url = |name: &'static str| {
+        std::env::var(name)
+            .ok()
+            .filter(|s| !s.is_empty())
+            .ok_or(Error::MissingSessionUrl(name))
+    };
+    let backend = std::env::var(GIGA_TEST_SESSION_BACKEND).unwrap_or_default();
+    match backend.to_lowercase().as_str() {
+        "" | "sqlite" => Ok(SessionBackendConfig::Sqlite),
+        "memory" => Ok(SessionBackendConfig::Memory),
+        "postgres" => Ok(SessionBackendConfig::Postgres(url(
+            GIGA_TEST_SESSION_POSTGRES_URL,
+        )?)),
+        "redis" => Ok(SessionBackendConfig::Redis(url(
+            GIGA_TEST_SESSION_REDIS_URL,
+        )?)),
+        _ => Err(Error::UnknownSessionBackend(backend)),
+    }
+}
+
+/// Sessions expire after this many days of inactivity
+pub(crate) fn session_expiry_days() -> Result<u64, Error> {
+    let days = std::env::var(GIGA_TEST_SESSION_EXPIRY_DAYS)
+        .map_or_else(|_| Ok(DEFAULT_SESSION_EXPIRY_DAYS), |s| s.parse::<u64>())
+        .map_err(Error::SessionExpiry)?;
+    if !(1..=MAX_SESSION_EXPIRY_DAYS).contains(&days) {
+        return Err(Error::SessionExpiryRange);
+    }
+    Ok(days)
+}
+
+/// How often expired sessions are deleted; 0 turns periodic cleanup off,
+/// e.g. when `sessions prune` is run from cron instead
+pub(crate) fn session_cleanup_interval() -> Result<Option<Duration>, Error> {
+    std::env::var(GIGA_TEST_SESSION_CLEANUP_MINUTES)
+        .map_or_else(
+            |_| Ok(DEFAULT_SESSION_CLEANUP_MINUTES),
+            |s| s.parse::<u64>(),
+        )
+        .map(|minutes| (minutes > 0).then(|| Duration::from_secs(minutes * 60)))
+        .map_err(Error::SessionCleanup)
+}
+
+/// How long analytics events are kept. Analytics are opt-in - they stay off
+/// when it's not set or 0.
+pub(crate) fn analytics_retention_days() -> Result<Option<u64>, Error> {
+    let days = std::env::var(GIGA_TEST_ANALYTICS_RETENTION_DAYS)
+        .map_or_else(|_| Ok(0), |s| s.parse::<u64>())
+        .map_err(Error::AnalyticsRetention)?;
+    if days > MAX_ANALYTICS_RETENTION_DAYS {
+        return Err(Error::AnalyticsRetentionRange);
+    }
+    Ok((days > 0).then_some(days))
+}
+
+/// Largest request body accepted, answer forms and imported progress files
+/// included
+pub(crate) fn max_body_bytes() -> Result<usize, Error> {
+    let bytes = std::env::var(GIGA_TEST_MAX_BODY_BYTES)
+        .map_or_else(|_| Ok(DEFAULT_MAX_BODY_BYTES), |s| s.parse::<usize>())
+        .map_err(Error::MaxBody)?;
+    if bytes == 0 {
+        return Err(Error::ZeroMaxBody);
+    }
+    Ok(bytes)
 }
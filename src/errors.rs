@@ -11,6 +11,8 @@ pub enum Error {
     NotFound,
     #[error("wrong size")]
     WrongSize,
+    #[error("request is too large")]
+    PayloadTooLarge,
     #[error("illegal characters")]
     IllegalCharacters,
     #[error("integer conversion error: {0}")]
@@ -21,4 +23,36 @@ pub enum Error {
     CookieParsing(String),
     #[error("could not render template")]
     Render(#[from] askama::Error),
+    #[error("this nickname is not allowed")]
+    NicknameNotAllowed,
+    #[error("test must be finished first")]
+    TestNotFinished,
+    #[error("test is already finished, answers can't be changed")]
+    TestFinished,
+    #[error("time is up, answers are no longer accepted")]
+    TimeExpired,
+    #[error("this part is not available yet")]
+    PartLocked,
+    #[error("this username is already taken")]
+    UsernameTaken,
+    #[error("wrong username or password")]
+    WrongCredentials,
+    #[error("could not hash password: {0}")]
+    PasswordHash(String),
+    #[error("this file or code is damaged or was not created by this site")]
+    InvalidSignature,
+    #[error("attempts with a time limit can only be exported once they are finished")]
+    TimedExport,
+    #[error("this file or code comes from an older version of the site, only finished attempts can be imported from it")]
+    OutdatedExport,
+    #[error("unknown question: {0}")]
+    UnknownQuestion(String),
+    #[error("could not render image: {0}")]
+    ImageRender(String),
+    #[error("invalid JSON: {0}")]
+    InvalidJson(String),
+    #[error("this form has expired or was sent from another site - go back, reload the page and try again")]
+    CsrfTokenMismatch,
+    #[error("database error: {0}")]
+    Database(#[from] tower_sessions_sqlx_store::sqlx::Error),
 }
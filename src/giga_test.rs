@@ -1,10 +1,33 @@
+use crate::db::ResponseRow;
 use crate::models::{
-    AnswerChoice, AnswersDB, PlaceBucket, Question, RawTest, Section, Test, TestPart,
+    AnswerChoice, AnswersDB, Attempt, ExportedProgress, PlaceBucket, Question, QuestionStats,
+    QuestionStatsData, QuickQuizDraw, RawTest, Schedule, Section, SignedProgress, Test, TestPart,
     TestPartTally, TestStateMainPageElem, TestStateMainPageTotals, TestStatePartPage,
     TestStatePartPageAnswerChoice, TestStatePartPageQuestion, TestStatePartPageSection,
-    UserResponse, UserResponseData,
+    TimedAttempt, UserResponse, UserResponseData, QUESTION_IDS,
 };
-use std::collections::HashMap;
+use crate::Error;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+static NICKNAME_DENYLIST: &str = include_str!("nickname_denylist.txt");
+const NICKNAME_MIN_LEN: usize = 3;
+const NICKNAME_MAX_LEN: usize = 24;
+pub(crate) const QUICK_QUIZ_DEFAULT_QUESTIONS: usize = 20;
+pub(crate) const QUICK_QUIZ_MAX_QUESTIONS: usize = 100;
+const EXPORT_VERSION: u8 = 2;
+/// Exports made before unfinished timed attempts were refused - they may have
+/// lost a time limit, so only finished attempts are accepted from them
+const EXPORT_VERSION_UNTIMED: u8 = 1;
+/// Flags byte of progress code
+const CODE_FINISHED: u8 = 1;
+const CODE_COUNT_CANCELED: u8 = 2;
+const CODE_SHUFFLED: u8 = 4;
+const EXPORT_CODE_MAC_LEN: usize = 16;
+const CONTENT_HASH_LEN: usize = 8;
 
 // Table with a number of points received by each participant of original competition,
 // copied from http://www.psxextreme.bmp.net.pl/gigatest.htm (Web Archive)
@@ -68,7 +91,29 @@ fn tally_test_part(
 
     let answered_q = answered_good_q + answered_bad_q;
 
-    TestPartTally::new(answered_q, total_q, answered_good_q, answered_bad_q)
+    // Correct answers are also split by canceled status, ignoring the display toggle,
+    // so placement can always follow original competition rules
+    let (answered_good_regular_q, answered_good_canceled_q) = test_part
+        .get_questions()
+        .iter()
+        .filter(|question| {
+            test_responses
+                .get(&question.id)
+                .is_some_and(|r| r.correct_answer == Some(r.user_answer))
+        })
+        .fold((0, 0), |(r, c), question| match question.canceled {
+            true => (r, c + 1),
+            false => (r + 1, c),
+        });
+
+    TestPartTally::new(
+        answered_q,
+        total_q,
+        answered_good_q,
+        answered_bad_q,
+        answered_good_regular_q,
+        answered_good_canceled_q,
+    )
 }
 
 pub(crate) fn get_index_tests_state(
@@ -84,6 +129,31 @@ pub(crate) fn get_index_tests_state(
         .collect()
 }
 
+pub(crate) fn get_place_bucket(place: usize) -> PlaceBucket {
+    match place {
+        1 => PlaceBucket::Winner,
+        2..=7 => PlaceBucket::ConsolationPrize,
+        8..=61 => PlaceBucket::NamePrinted,
+        62..=232 => PlaceBucket::NameWebsite,
+        _ => PlaceBucket::Loser,
+    }
+}
+
+/// Number of attempts in each prize bucket, from attempts per place
+pub(crate) fn place_bucket_counts(place_counts: &[(usize, usize)]) -> Vec<(PlaceBucket, usize)> {
+    PlaceBucket::ALL
+        .into_iter()
+        .map(|bucket| {
+            let attempts = place_counts
+                .iter()
+                .filter(|(place, _)| get_place_bucket(*place) == bucket)
+                .map(|(_, attempts)| attempts)
+                .sum();
+            (bucket, attempts)
+        })
+        .collect()
+}
+
 pub(crate) fn get_index_totals(
     index_tests_state: &[TestStateMainPageElem],
 ) -> TestStateMainPageTotals {
@@ -92,19 +162,23 @@ pub(crate) fn get_index_totals(
             (g + x.answered_good_q, b + x.answered_bad_q, t + x.total_q)
         });
     let answered_total_q = answered_good_q + answered_bad_q;
-    let place = get_user_place(answered_good_q);
-    let place_bucket = match place {
-        1 => PlaceBucket::Winner,
-        2..=7 => PlaceBucket::ConsolationPrize,
-        8..=61 => PlaceBucket::NamePrinted,
-        62..=232 => PlaceBucket::NameWebsite,
-        _ => PlaceBucket::Loser,
-    };
+    let (answered_good_q_without_canceled, answered_good_canceled_q) =
+        index_tests_state.iter().fold((0, 0), |(r, c), x| {
+            (
+                r + x.answered_good_regular_q,
+                c + x.answered_good_canceled_q,
+            )
+        });
+    let answered_good_q_with_canceled = answered_good_q_without_canceled + answered_good_canceled_q;
+    let place = get_user_place(answered_good_q_without_canceled);
+    let place_bucket = get_place_bucket(place);
     TestStateMainPageTotals {
         answered_good_q,
         answered_bad_q,
         answered_total_q,
         total_q,
+        answered_good_q_without_canceled,
+        answered_good_q_with_canceled,
         place,
         place_bucket,
     }
@@ -114,14 +188,17 @@ pub(crate) fn get_part_state(
     test_part: &TestPart,
     test_responses: &UserResponseData,
     count_canceled: bool,
+    questions_stats: &QuestionStatsData,
+    shuffle_seed: Option<u64>,
 ) -> TestStatePartPage {
     fn generate_answers(
         question_id: &str,
         answer_id: char,
+        canonical_id: char,
         answer: &AnswerChoice,
         user_answer: Option<char>,
     ) -> (char, TestStatePartPageAnswerChoice) {
-        let user_selected = user_answer.is_some_and(|r| r == answer_id);
+        let user_selected = user_answer.is_some_and(|r| r == canonical_id);
         let choice_class = if answer.correct {
             "correct"
         } else if user_selected {
@@ -141,21 +218,41 @@ pub(crate) fn get_part_state(
         (answer_id, obj)
     }
 
+    // Everything player sees uses displayed letters, which differ from
+    // canonical ones when choices are shuffled
     let generate_questions = |question: &Question| {
+        let order = choice_order(shuffle_seed, question);
         let user_answer = test_responses.get(&question.id).map(|r| r.user_answer);
-        let new_answers = question
-            .choices
+        let new_answers = QUESTION_IDS
             .iter()
-            .map(|(answer_id, answer)| {
-                generate_answers(&question.id, *answer_id, answer, user_answer)
+            .zip(&order)
+            .filter_map(|(answer_id, canonical_id)| {
+                let answer = question.choices.get(canonical_id)?;
+                Some(generate_answers(
+                    &question.id,
+                    *answer_id,
+                    *canonical_id,
+                    answer,
+                    user_answer,
+                ))
             })
             .collect();
+        let stats = questions_stats.get(&question.id).map(|stats| {
+            let mut stats = stats.clone();
+            stats.picks = stats
+                .picks
+                .iter()
+                .filter_map(|(c, picks)| Some((to_displayed(&order, *c)?, *picks)))
+                .collect();
+            stats
+        });
         TestStatePartPageQuestion {
             id: question.id.clone(),
             question: question.question.clone(),
             choices: new_answers,
-            user_answer,
+            user_answer: user_answer.and_then(|c| to_displayed(&order, c)),
             canceled: question.canceled,
+            stats,
         }
     };
 
@@ -185,6 +282,163 @@ pub(crate) fn get_part_state(
     }
 }
 
+/// Canonical choice letters of a question, in order they are displayed.
+/// Without a seed this is just the order from TOML file.
+pub(crate) fn choice_order(shuffle_seed: Option<u64>, question: &Question) -> Vec<char> {
+    let mut order: Vec<char> = question.choices.keys().copied().collect();
+    let Some(seed) = shuffle_seed else {
+        return order;
+    };
+    // Question ID is mixed in, so each question gets its own order
+    let seed = question.id.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    }) ^ seed;
+    seeded_shuffle(&mut order, seed);
+    order
+}
+
+/// Fisher-Yates with splitmix64 as PRNG. Hand-rolled, so the same seed gives
+/// the same order regardless of library updates.
+fn seeded_shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    for i in (1..items.len()).rev() {
+        let j = usize::try_from(next() % (i as u64 + 1)).unwrap_or_default();
+        items.swap(i, j);
+    }
+}
+
+/// Draw questions for a quick quiz. Canceled questions are never drawn.
+/// Drawn questions are grouped back into their sections, so section
+/// introductions can be shown. Returns `None` for unknown part.
+pub(crate) fn draw_quick_quiz(
+    test: &Test,
+    draw: &QuickQuizDraw,
+    part_available: impl Fn(&str) -> bool,
+) -> Option<TestPart> {
+    if draw
+        .part
+        .as_ref()
+        .is_some_and(|part_id| test.get(part_id).is_none())
+    {
+        return None;
+    }
+    let mut candidates: Vec<(&str, &str, &Question)> = test
+        .iter()
+        .filter(|(part_id, _)| draw.part.as_deref().is_none_or(|p| p == *part_id))
+        .filter(|(part_id, _)| part_available(part_id))
+        .flat_map(|(part_id, part)| {
+            part.sections.iter().flat_map(move |(section_id, section)| {
+                section
+                    .questions
+                    .iter()
+                    .filter(|question| !question.canceled)
+                    .map(move |question| (part_id.as_str(), section_id.as_str(), question))
+            })
+        })
+        .collect();
+    seeded_shuffle(&mut candidates, draw.seed);
+    candidates.truncate(draw.count);
+    // Position within the original test, so questions keep their usual order
+    candidates.sort_by_cached_key(|(_, _, question)| {
+        question
+            .id
+            .trim_start_matches('q')
+            .split('_')
+            .map(|n| n.parse::<usize>().unwrap_or_default())
+            .collect::<Vec<_>>()
+    });
+
+    let mut sections: BTreeMap<String, Section> = BTreeMap::new();
+    for (part_id, section_id, question) in candidates {
+        let part_section = test.get(part_id)?.sections.get(section_id)?;
+        // Keys only order sections; zero padding keeps part 10 after part 9
+        let key = format!("{part_id:0>4}_{section_id:0>4}");
+        sections
+            .entry(key)
+            .or_insert_with(|| Section {
+                introduction: part_section.introduction.clone(),
+                questions: Vec::new(),
+            })
+            .questions
+            .push(question.clone());
+    }
+    Some(TestPart {
+        introduction: String::new(),
+        sections,
+    })
+}
+
+/// Tally of a drawn quick quiz, canceled questions are never drawn anyway
+pub(crate) fn tally_quick_quiz(
+    quiz: &TestPart,
+    test_responses: &UserResponseData,
+) -> TestStateMainPageElem {
+    TestStateMainPageElem::from("szybki-test", tally_test_part(quiz, test_responses, true))
+}
+
+fn to_displayed(order: &[char], canonical: char) -> Option<char> {
+    let index = order.iter().position(|c| *c == canonical)?;
+    QUESTION_IDS.get(index).copied()
+}
+
+fn to_canonical(order: &[char], displayed: char) -> Option<char> {
+    let index = QUESTION_IDS.iter().position(|c| *c == displayed)?;
+    order.get(index).copied()
+}
+
+/// Translate letters submitted by player back to canonical ones
+pub(crate) fn canonical_form_data(
+    form_data: &HashMap<String, String>,
+    test: &Test,
+    shuffle_seed: Option<u64>,
+) -> HashMap<String, String> {
+    if shuffle_seed.is_none() {
+        return form_data.clone();
+    }
+    form_data
+        .iter()
+        .map(|(question_id, value)| {
+            let canonical = test
+                .get_question(question_id)
+                .zip(value.chars().next())
+                .and_then(|(question, displayed)| {
+                    to_canonical(&choice_order(shuffle_seed, question), displayed)
+                })
+                .map_or_else(|| value.clone(), String::from);
+            (question_id.clone(), canonical)
+        })
+        .collect()
+}
+
+/// Check submitted answers before they are saved. Every field has to be
+/// a question of the test, with one of that question's choice letters.
+/// Shuffling only reorders the letters, so displayed ones are checked too.
+pub(crate) fn validate_form_data(
+    form_data: &HashMap<String, String>,
+    test: &Test,
+) -> Result<(), Error> {
+    for (question_id, value) in form_data {
+        let question = test
+            .get_question(question_id)
+            .ok_or(Error::IllegalCharacters)?;
+        let mut letters = value.chars();
+        let (Some(letter), None) = (letters.next(), letters.next()) else {
+            return Err(Error::WrongSize);
+        };
+        if !question.choices.contains_key(&letter) {
+            return Err(Error::IllegalCharacters);
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn responses_from_form_data(
     form_data: &HashMap<String, String>,
     questions_db: &AnswersDB,
@@ -206,9 +460,503 @@ pub(crate) fn responses_from_form_data(
         .collect()
 }
 
+/// Fingerprint of correct answers in the test, stored along with player's
+/// answers to tell which content they were given to
+pub(crate) fn content_hash(questions_db: &AnswersDB) -> String {
+    let mut answers: Vec<_> = questions_db.iter().collect();
+    answers.sort();
+    let mut hasher = Sha256::new();
+    for (question_id, correct_answer) in answers {
+        hasher.update(question_id.as_bytes());
+        hasher.update(b"=");
+        if let Some(correct_answer) = correct_answer {
+            hasher.update(correct_answer.to_string().as_bytes());
+        }
+        hasher.update(b"\n");
+    }
+    hasher.finalize()[..CONTENT_HASH_LEN]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Answers from `responses` table, graded against current test. Answers to
+/// questions no longer in the test are skipped.
+pub(crate) fn responses_from_rows(
+    rows: &[ResponseRow],
+    questions_db: &AnswersDB,
+) -> UserResponseData {
+    rows.iter()
+        .filter_map(|(question_id, answer)| {
+            let user_answer = answer.chars().next()?;
+            let correct_answer = *questions_db.get(question_id)?;
+            Some((
+                question_id.clone(),
+                UserResponse {
+                    user_answer,
+                    correct_answer,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Grade responses saved elsewhere, e.g. in archived attempts, against
+/// current test
+pub(crate) fn regrade_responses(responses: &mut UserResponseData, questions_db: &AnswersDB) {
+    responses.retain(
+        |question_id, response| match questions_db.get(question_id) {
+            Some(correct_answer) => {
+                response.correct_answer = *correct_answer;
+                true
+            }
+            None => false,
+        },
+    );
+}
+
+pub(crate) fn question_stats_from_rows(
+    rows: &[(String, String, i64)],
+    questions_db: &AnswersDB,
+) -> QuestionStatsData {
+    let mut stats = QuestionStatsData::new();
+    for (question_id, choice, picks) in rows {
+        let (Some(choice), Ok(picks)) = (choice.chars().next(), usize::try_from(*picks)) else {
+            continue;
+        };
+        let Some(correct_answer) = questions_db.get(question_id) else {
+            continue;
+        };
+        let question_stats: &mut QuestionStats = stats.entry(question_id.clone()).or_default();
+        question_stats.answered += picks;
+        if *correct_answer == Some(choice) {
+            question_stats.answered_good += picks;
+        }
+        *question_stats.picks.entry(choice).or_default() += picks;
+    }
+    stats
+}
+
+/// Questions answered correctly least often, worst first. Questions without
+/// correct answer are left out, as nobody could get them right.
+pub(crate) fn most_missed_questions(
+    questions_db: &AnswersDB,
+    stats: &QuestionStatsData,
+    limit: usize,
+) -> Vec<(String, QuestionStats)> {
+    let mut missed: Vec<(String, QuestionStats)> = stats
+        .iter()
+        .filter(|(question_id, question_stats)| {
+            question_stats.answered > question_stats.answered_good
+                && questions_db.get(*question_id).copied().flatten().is_some()
+        })
+        .map(|(question_id, question_stats)| (question_id.clone(), question_stats.clone()))
+        .collect();
+    missed.sort_by(|(a_id, a), (b_id, b)| {
+        a.correct_percent()
+            .cmp(&b.correct_percent())
+            .then(b.answered.cmp(&a.answered))
+            .then(a_id.cmp(b_id))
+    });
+    missed.truncate(limit);
+    missed
+}
+
+pub(crate) fn question_stats_csv(questions_db: &AnswersDB, stats: &QuestionStatsData) -> String {
+    let mut question_ids: Vec<&String> = questions_db.keys().collect();
+    question_ids.sort_by_key(|id| {
+        id.trim_start_matches('q')
+            .split('_')
+            .map(|n| n.parse().unwrap_or(usize::MAX))
+            .collect::<Vec<usize>>()
+    });
+
+    let header = std::iter::once("question_id,correct_answer,answered,correct_percent".to_string())
+        .chain(QUESTION_IDS.iter().map(|c| format!("picked_{c}_percent")))
+        .collect::<Vec<String>>()
+        .join(",");
+    let rows = question_ids.into_iter().map(|question_id| {
+        let question_stats = stats.get(question_id).cloned().unwrap_or_default();
+        let correct_answer = questions_db
+            .get(question_id)
+            .copied()
+            .flatten()
+            .map(String::from)
+            .unwrap_or_default();
+        std::iter::once(question_id.clone())
+            .chain([
+                correct_answer,
+                question_stats.answered.to_string(),
+                question_stats.correct_percent().to_string(),
+            ])
+            .chain(
+                QUESTION_IDS
+                    .iter()
+                    .map(|c| question_stats.pick_percent(*c).to_string()),
+            )
+            .collect::<Vec<String>>()
+            .join(",")
+    });
+    std::iter::once(header)
+        .chain(rows)
+        .map(|line| line + "\n")
+        .collect()
+}
+
+pub(crate) fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Human-readable remaining time, same format as countdown script uses
+pub(crate) fn format_duration(seconds: u64) -> String {
+    let (days, rest) = (seconds / 86400, seconds % 86400);
+    let (hours, minutes, seconds) = (rest / 3600, rest % 3600 / 60, rest % 60);
+    if days > 0 {
+        format!("{days} d {hours:02}:{minutes:02}:{seconds:02}")
+    } else if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+pub(crate) fn apply_schedule(
+    index_tests_state: &mut [TestStateMainPageElem],
+    schedule: &Schedule,
+    now: u64,
+) {
+    for elem in index_tests_state {
+        elem.seconds_to_unlock = schedule.seconds_to_unlock(&elem.test_id, now);
+        elem.seconds_to_deadline = schedule.seconds_to_deadline(&elem.test_id, now);
+    }
+}
+
+/// Part ID is encoded in question ID, see `Section::from_raw`
+pub(crate) fn question_part_id(question_id: &str) -> Option<&str> {
+    question_id.strip_prefix('q')?.split('_').next()
+}
+
+/// Pair every attempt with change of correct answers since previous one
+pub(crate) fn attempts_progress(attempts: Vec<Attempt>) -> Vec<(Attempt, Option<isize>)> {
+    let mut previous: Option<usize> = None;
+    attempts
+        .into_iter()
+        .map(|attempt| {
+            let current = attempt.answered_good_q_without_canceled;
+            let change = previous.map(|p| current.cast_signed() - p.cast_signed());
+            previous = Some(current);
+            (attempt, change)
+        })
+        .collect()
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn progress_mac(secret_key: &str, data: &[u8]) -> Result<HmacSha256, Error> {
+    let mut mac =
+        HmacSha256::new_from_slice(secret_key.as_bytes()).map_err(|_| Error::InvalidSignature)?;
+    mac.update(data);
+    Ok(mac)
+}
+
+/// Time limits are not exported, so an unfinished timed attempt can't be -
+/// it would come back without a deadline
+pub(crate) fn export_progress(
+    test_responses: &UserResponseData,
+    finished: bool,
+    count_canceled: bool,
+    timer: Option<&TimedAttempt>,
+    shuffle_seed: Option<u64>,
+) -> Result<ExportedProgress, Error> {
+    if timer.is_some() && !finished {
+        return Err(Error::TimedExport);
+    }
+    Ok(ExportedProgress {
+        version: EXPORT_VERSION,
+        responses: test_responses
+            .iter()
+            .map(|(question_id, response)| (question_id.clone(), response.user_answer))
+            .collect(),
+        finished,
+        count_canceled,
+        shuffle_seed,
+    })
+}
+
+fn check_export_version(progress: ExportedProgress) -> Result<ExportedProgress, Error> {
+    match progress.version {
+        EXPORT_VERSION => Ok(progress),
+        EXPORT_VERSION_UNTIMED if progress.finished => Ok(progress),
+        EXPORT_VERSION_UNTIMED => Err(Error::OutdatedExport),
+        _ => Err(Error::InvalidSignature),
+    }
+}
+
+/// Signed JSON file. Responses are kept in `BTreeMap`, so serializing the
+/// same progress always gives the same bytes and signature can be checked
+/// after parsing.
+pub(crate) fn progress_file(progress: ExportedProgress, secret_key: &str) -> Result<String, Error> {
+    let data = serde_json::to_vec(&progress).map_err(|_| Error::InvalidSignature)?;
+    let signature = progress_mac(secret_key, &data)?.finalize().into_bytes();
+    let signed = SignedProgress {
+        progress,
+        signature: URL_SAFE_NO_PAD.encode(signature),
+    };
+    serde_json::to_string_pretty(&signed).map_err(|_| Error::InvalidSignature)
+}
+
+/// Short code: version, flags, one nibble per question of `AnswersDB` in ID
+/// order (0 - no answer, otherwise position in `QUESTION_IDS` plus one),
+/// shuffle seed if there is one and truncated signature, all in URL-safe
+/// base64
+pub(crate) fn progress_code(
+    progress: &ExportedProgress,
+    questions_db: &AnswersDB,
+    secret_key: &str,
+) -> Result<String, Error> {
+    let mut question_ids: Vec<&String> = questions_db.keys().collect();
+    question_ids.sort_unstable();
+    let nibbles: Vec<u8> = question_ids
+        .iter()
+        .map(|question_id| {
+            progress
+                .responses
+                .get(*question_id)
+                .and_then(|answer| QUESTION_IDS.iter().position(|c| c == answer))
+                .map_or(0, |index| u8::try_from(index + 1).unwrap_or_default())
+        })
+        .collect();
+    let mut flags = 0;
+    for (set, flag) in [
+        (progress.finished, CODE_FINISHED),
+        (progress.count_canceled, CODE_COUNT_CANCELED),
+        (progress.shuffle_seed.is_some(), CODE_SHUFFLED),
+    ] {
+        if set {
+            flags |= flag;
+        }
+    }
+    let mut data = vec![progress.version, flags];
+    data.extend(
+        nibbles
+            .chunks(2)
+            .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or_default()),
+    );
+    if let Some(seed) = progress.shuffle_seed {
+        data.extend(seed.to_be_bytes());
+    }
+    let signature = progress_mac(secret_key, &data)?.finalize().into_bytes();
+    data.extend(&signature[..EXPORT_CODE_MAC_LEN]);
+    Ok(URL_SAFE_NO_PAD.encode(data))
+}
+
+/// Parse exported file or code, checking its signature
+pub(crate) fn import_progress(
+    input: &str,
+    questions_db: &AnswersDB,
+    secret_key: &str,
+) -> Result<ExportedProgress, Error> {
+    let input = input.trim();
+    if input.starts_with('{') {
+        let signed: SignedProgress =
+            serde_json::from_str(input).map_err(|_| Error::InvalidSignature)?;
+        let data = serde_json::to_vec(&signed.progress).map_err(|_| Error::InvalidSignature)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(&signed.signature)
+            .map_err(|_| Error::InvalidSignature)?;
+        progress_mac(secret_key, &data)?
+            .verify_slice(&signature)
+            .map_err(|_| Error::InvalidSignature)?;
+        return check_export_version(signed.progress);
+    }
+
+    let bytes = URL_SAFE_NO_PAD
+        .decode(input)
+        .map_err(|_| Error::InvalidSignature)?;
+    let (data, signature) = bytes
+        .split_at_checked(bytes.len().saturating_sub(EXPORT_CODE_MAC_LEN))
+        .ok_or(Error::InvalidSignature)?;
+    progress_mac(secret_key, data)?
+        .verify_truncated_left(signature)
+        .map_err(|_| Error::InvalidSignature)?;
+    let [version, flags, packed @ ..] = data else {
+        return Err(Error::InvalidSignature);
+    };
+    let mut question_ids: Vec<&String> = questions_db.keys().collect();
+    question_ids.sort_unstable();
+    let (packed, seed) = packed
+        .split_at_checked(question_ids.len().div_ceil(2))
+        .ok_or(Error::InvalidSignature)?;
+    let shuffle_seed = if flags & CODE_SHUFFLED == 0 {
+        None
+    } else {
+        Some(u64::from_be_bytes(
+            seed.try_into().map_err(|_| Error::InvalidSignature)?,
+        ))
+    };
+    if shuffle_seed.is_none() && !seed.is_empty() {
+        return Err(Error::InvalidSignature);
+    }
+    let responses = packed
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .zip(question_ids)
+        .filter(|(nibble, _)| *nibble != 0)
+        .map(|(nibble, question_id)| {
+            let answer = QUESTION_IDS
+                .get(usize::from(nibble - 1))
+                .ok_or(Error::IllegalCharacters)?;
+            Ok((question_id.clone(), *answer))
+        })
+        .collect::<Result<_, Error>>()?;
+    check_export_version(ExportedProgress {
+        version: *version,
+        responses,
+        finished: flags & CODE_FINISHED != 0,
+        count_canceled: flags & CODE_COUNT_CANCELED != 0,
+        shuffle_seed,
+    })
+}
+
+/// Turn imported answers into session data, rejecting questions that are
+/// not in the test
+pub(crate) fn responses_from_export(
+    progress: &ExportedProgress,
+    questions_db: &AnswersDB,
+) -> Result<UserResponseData, Error> {
+    progress
+        .responses
+        .iter()
+        .map(|(question_id, answer)| {
+            let correct_answer = questions_db
+                .get(question_id)
+                .ok_or_else(|| Error::UnknownQuestion(question_id.clone()))?;
+            if !QUESTION_IDS.contains(answer) {
+                return Err(Error::IllegalCharacters);
+            }
+            Ok((
+                question_id.clone(),
+                UserResponse {
+                    user_answer: *answer,
+                    correct_answer: *correct_answer,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Polish noun form for a number: 1 odpowiedź, 2 odpowiedzi, 5 odpowiedzi
+pub(crate) fn polish_plural<'a>(n: &usize, one: &'a str, few: &'a str, many: &'a str) -> &'a str {
+    match (n % 10, n % 100) {
+        _ if *n == 1 => one,
+        (2..=4, rem) if !(12..=14).contains(&rem) => few,
+        _ => many,
+    }
+}
+
+/// Check nickname submitted to leaderboard and return it normalized
+pub(crate) fn validate_nickname(nickname: &str) -> Result<String, Error> {
+    let nickname = nickname.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if !(NICKNAME_MIN_LEN..=NICKNAME_MAX_LEN).contains(&nickname.chars().count()) {
+        return Err(Error::WrongSize);
+    }
+    if !nickname
+        .chars()
+        .all(|c| c.is_alphanumeric() || " -_.".contains(c))
+    {
+        return Err(Error::IllegalCharacters);
+    }
+    let lowercase = nickname.to_lowercase();
+    let denied = NICKNAME_DENYLIST
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty() && !word.starts_with('#'))
+        .any(|word| lowercase.contains(word));
+    if denied {
+        return Err(Error::NicknameNotAllowed);
+    }
+    Ok(nickname)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::{SessionResponses, TimeLimit, TimeLimitKind};
+
+    const SMALL_TEST: &str = r#"
+[1]
+introduction = "Part"
+
+[1.sections.1]
+introduction = "Section"
+
+[[1.sections.1.questions]]
+question = "Regular"
+
+[[1.sections.1.questions.choices]]
+answer = "good"
+correct = true
+
+[[1.sections.1.questions.choices]]
+answer = "bad"
+correct = false
+
+[[1.sections.1.questions]]
+question = "Canceled"
+canceled = true
+
+[[1.sections.1.questions.choices]]
+answer = "good"
+correct = true
+
+[[1.sections.1.questions.choices]]
+answer = "bad"
+correct = false
+"#;
+
+    fn small_test() -> Test {
+        toml::from_str::<RawTest>(SMALL_TEST).unwrap().into()
+    }
+
+    fn all_correct_responses(test: &Test) -> UserResponseData {
+        test.get_correct_answers()
+            .into_iter()
+            .map(|(id, correct_answer)| {
+                let ur = UserResponse {
+                    user_answer: correct_answer.unwrap(),
+                    correct_answer,
+                };
+                (id, ur)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_totals_count_canceled() {
+        let test = small_test();
+        let responses = all_correct_responses(&test);
+        let state = get_index_tests_state(&test, &responses, true);
+        let totals = get_index_totals(&state);
+        assert_eq!(totals.answered_good_q, 2);
+        assert_eq!(totals.answered_good_q_with_canceled, 2);
+        assert_eq!(totals.answered_good_q_without_canceled, 1);
+        assert_eq!(totals.place, get_user_place(1));
+    }
+
+    #[test]
+    fn test_totals_skip_canceled() {
+        let test = small_test();
+        let responses = all_correct_responses(&test);
+        let state = get_index_tests_state(&test, &responses, false);
+        let totals = get_index_totals(&state);
+        assert_eq!(totals.answered_good_q, 1);
+        assert_eq!(totals.answered_good_q_with_canceled, 2);
+        assert_eq!(totals.answered_good_q_without_canceled, 1);
+        assert_eq!(totals.place, get_user_place(1));
+    }
 
     #[test]
     fn test_user_place_best() {
@@ -239,4 +987,464 @@ mod tests {
         let user_place = get_user_place(0);
         assert_eq!(user_place, 233);
     }
+
+    #[test]
+    fn test_question_stats_from_rows() {
+        let test = small_test();
+        let questions_db = test.get_correct_answers();
+        let rows = vec![
+            ("q1_1_0".to_string(), "A".to_string(), 1),
+            ("q1_1_0".to_string(), "B".to_string(), 2),
+            ("q9_9_9".to_string(), "A".to_string(), 5),
+        ];
+        let stats = question_stats_from_rows(&rows, &questions_db);
+        assert_eq!(stats.len(), 1);
+        let question_stats = &stats["q1_1_0"];
+        assert_eq!(question_stats.answered, 3);
+        assert_eq!(question_stats.answered_good, 1);
+        assert_eq!(question_stats.correct_percent(), 33);
+        assert_eq!(question_stats.most_picked(), Some('B'));
+    }
+
+    #[test]
+    fn test_most_missed_questions() {
+        let test = small_test();
+        let questions_db = test.get_correct_answers();
+        let rows = vec![
+            ("q1_1_0".to_string(), "A".to_string(), 1),
+            ("q1_1_0".to_string(), "B".to_string(), 2),
+            ("q1_1_1".to_string(), "A".to_string(), 1),
+            ("q1_1_1".to_string(), "B".to_string(), 1),
+        ];
+        let stats = question_stats_from_rows(&rows, &questions_db);
+        let missed: Vec<String> = most_missed_questions(&questions_db, &stats, 5)
+            .into_iter()
+            .map(|(question_id, _)| question_id)
+            .collect();
+        assert_eq!(missed, ["q1_1_0", "q1_1_1"]);
+        assert_eq!(most_missed_questions(&questions_db, &stats, 1).len(), 1);
+
+        let all_good = question_stats_from_rows(&rows[..1], &questions_db);
+        assert!(most_missed_questions(&questions_db, &all_good, 5).is_empty());
+    }
+
+    #[test]
+    fn test_place_bucket_counts() {
+        let counts = place_bucket_counts(&[(1, 2), (5, 1), (7, 3), (100, 1), (500, 4)]);
+        assert_eq!(
+            counts,
+            [
+                (PlaceBucket::Winner, 2),
+                (PlaceBucket::ConsolationPrize, 4),
+                (PlaceBucket::NamePrinted, 0),
+                (PlaceBucket::NameWebsite, 1),
+                (PlaceBucket::Loser, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_nickname() {
+        assert_eq!(validate_nickname("  Mr.   Ś ").unwrap(), "Mr. Ś");
+        assert!(matches!(validate_nickname("ab"), Err(Error::WrongSize)));
+        assert!(matches!(
+            validate_nickname("<script>"),
+            Err(Error::IllegalCharacters)
+        ));
+        assert!(matches!(
+            validate_nickname("SuperAdmin"),
+            Err(Error::NicknameNotAllowed)
+        ));
+    }
+
+    #[test]
+    fn test_attempts_progress() {
+        let attempt = |good| Attempt {
+            id: 0,
+            finished_at: String::new(),
+            answered_good_q: good,
+            answered_bad_q: 0,
+            total_q: 296,
+            answered_good_q_without_canceled: good,
+            answered_good_q_with_canceled: good,
+            place: get_user_place(good),
+            count_canceled: false,
+            responses: UserResponseData::new(),
+            time_limit: None,
+            shuffle_seed: None,
+        };
+        let progress = attempts_progress(vec![attempt(10), attempt(15), attempt(12)]);
+        let changes: Vec<Option<isize>> = progress.iter().map(|(_, c)| *c).collect();
+        assert_eq!(changes, vec![None, Some(5), Some(-3)]);
+    }
+
+    #[test]
+    fn test_question_part_id() {
+        assert_eq!(question_part_id("q3_1_25"), Some("3"));
+        assert_eq!(question_part_id("x3_1_25"), None);
+    }
+
+    #[test]
+    fn test_timed_attempt_whole_test() {
+        let limit = TimeLimit {
+            kind: TimeLimitKind::WholeTest,
+            minutes: 10,
+        };
+        let mut timer = TimedAttempt::new(limit, 1000);
+        assert!(!timer.start_part("1", 1200));
+        assert_eq!(timer.deadline("1"), Some(1600));
+        assert_eq!(timer.deadline("6"), Some(1600));
+    }
+
+    #[test]
+    fn test_timed_attempt_per_part() {
+        let limit = TimeLimit {
+            kind: TimeLimitKind::PerPart,
+            minutes: 10,
+        };
+        let mut timer = TimedAttempt::new(limit, 1000);
+        assert_eq!(timer.deadline("1"), None);
+        assert!(timer.start_part("1", 1200));
+        assert!(!timer.start_part("1", 1500));
+        assert_eq!(timer.deadline("1"), Some(1800));
+        assert_eq!(timer.deadline("2"), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(59), "0:59");
+        assert_eq!(format_duration(3661), "1:01:01");
+        assert_eq!(format_duration(2 * 86400 + 5), "2 d 00:00:05");
+    }
+
+    #[test]
+    fn test_schedule() {
+        let schedule: Schedule = toml::from_str(
+            r#"
+[parts.1]
+unlock = "1970-01-01T00:16:40Z"
+deadline = "1970-01-01T00:33:20Z"
+"#,
+        )
+        .unwrap();
+        assert_eq!(schedule.seconds_to_unlock("1", 400), Some(600));
+        assert_eq!(schedule.seconds_to_unlock("1", 1000), None);
+        assert_eq!(schedule.seconds_to_unlock("2", 0), None);
+        assert_eq!(schedule.seconds_to_deadline("1", 1500), Some(500));
+        assert_eq!(schedule.seconds_to_deadline("1", 2500), Some(0));
+        assert_eq!(schedule.seconds_to_deadline("2", 0), None);
+        assert!(!schedule.practice_available("1", 1500));
+        assert!(schedule.practice_available("1", 2500));
+        assert!(schedule.practice_available("2", 0));
+    }
+
+    #[test]
+    fn test_choice_order_without_seed() {
+        let test = small_test();
+        let question = test.get_question("q1_1_0").unwrap();
+        assert_eq!(choice_order(None, question), vec!['A', 'B']);
+    }
+
+    #[test]
+    fn test_choice_order_shuffled() {
+        let test: Test = get_giga_test(&|s| s.to_string());
+        let question = test.get_question("q1_1_0").unwrap();
+        let order = choice_order(Some(42), question);
+        assert_eq!(order, choice_order(Some(42), question));
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, vec!['A', 'B', 'C', 'D']);
+        let differs = (0..20).any(|seed| choice_order(Some(seed), question) != order);
+        assert!(differs);
+    }
+
+    #[test]
+    fn test_canonical_form_data() {
+        let test: Test = get_giga_test(&|s| s.to_string());
+        let question = test.get_question("q1_1_0").unwrap();
+        let order = choice_order(Some(7), question);
+        let displayed = to_displayed(&order, 'C').unwrap();
+        let form = HashMap::from([
+            ("q1_1_0".to_string(), displayed.to_string()),
+            ("unknown".to_string(), "Z".to_string()),
+        ]);
+        let canonical = canonical_form_data(&form, &test, Some(7));
+        assert_eq!(canonical["q1_1_0"], "C");
+        assert_eq!(canonical["unknown"], "Z");
+    }
+
+    #[test]
+    fn test_draw_quick_quiz() {
+        let test: Test = get_giga_test(&|s| s.to_string());
+        let draw = QuickQuizDraw {
+            seed: 3,
+            count: 25,
+            part: None,
+        };
+        let quiz = draw_quick_quiz(&test, &draw, |_| true).unwrap();
+        let ids: Vec<String> = quiz.get_questions().iter().map(|q| q.id.clone()).collect();
+        assert_eq!(ids.len(), 25);
+        assert!(quiz.get_questions().iter().all(|q| !q.canceled));
+        let again = draw_quick_quiz(&test, &draw, |_| true).unwrap();
+        let again_ids: Vec<String> = again.get_questions().iter().map(|q| q.id.clone()).collect();
+        assert_eq!(ids, again_ids);
+    }
+
+    #[test]
+    fn test_draw_quick_quiz_part_filter() {
+        let test: Test = get_giga_test(&|s| s.to_string());
+        let draw = QuickQuizDraw {
+            seed: 3,
+            count: 10,
+            part: Some("2".to_string()),
+        };
+        let quiz = draw_quick_quiz(&test, &draw, |_| true).unwrap();
+        assert!(quiz
+            .get_questions()
+            .iter()
+            .all(|q| question_part_id(&q.id) == Some("2")));
+        let unknown = QuickQuizDraw {
+            part: Some("99".to_string()),
+            ..draw
+        };
+        assert!(draw_quick_quiz(&test, &unknown, |_| true).is_none());
+    }
+
+    #[test]
+    fn test_quick_quiz_url() {
+        let draw = QuickQuizDraw {
+            seed: 3,
+            count: 10,
+            part: None,
+        };
+        assert_eq!(draw.url(), "/szybki-test?ziarno=3&pytan=10");
+        let draw = QuickQuizDraw {
+            part: Some("2&pytan=1 x".to_string()),
+            ..draw
+        };
+        assert_eq!(
+            draw.url(),
+            "/szybki-test?ziarno=3&pytan=10&czesc=2%26pytan%3D1+x"
+        );
+    }
+
+    #[test]
+    fn test_quick_quiz_tally() {
+        let test = small_test();
+        let draw = QuickQuizDraw {
+            seed: 1,
+            count: 10,
+            part: None,
+        };
+        let quiz = draw_quick_quiz(&test, &draw, |_| true).unwrap();
+        let tally = tally_quick_quiz(&quiz, &all_correct_responses(&test));
+        assert_eq!(tally.total_q, 1);
+        assert_eq!(tally.answered_good_q, 1);
+        assert_eq!(tally.answered_bad_q, 0);
+    }
+
+    #[test]
+    fn test_validate_form_data() {
+        let test = small_test();
+        let form = |answers: &[(&str, &str)]| -> HashMap<String, String> {
+            answers
+                .iter()
+                .map(|(id, value)| ((*id).to_string(), (*value).to_string()))
+                .collect()
+        };
+        assert!(validate_form_data(&form(&[("q1_1_0", "A"), ("q1_1_1", "B")]), &test).is_ok());
+        assert!(matches!(
+            validate_form_data(&form(&[("q1_1_0", "A"), ("q1_1_2", "A")]), &test),
+            Err(Error::IllegalCharacters)
+        ));
+        assert!(matches!(
+            validate_form_data(&form(&[("q1_1_0", "C")]), &test),
+            Err(Error::IllegalCharacters)
+        ));
+        assert!(matches!(
+            validate_form_data(&form(&[("q1_1_0", "AB")]), &test),
+            Err(Error::WrongSize)
+        ));
+        assert!(matches!(
+            validate_form_data(&form(&[("q1_1_0", "")]), &test),
+            Err(Error::WrongSize)
+        ));
+    }
+
+    #[test]
+    fn test_progress_file_roundtrip() {
+        let test: Test = get_giga_test(&|s| s.to_string());
+        let questions_db = test.get_correct_answers();
+        let responses = responses_from_form_data(
+            &HashMap::from([("q1_1_0".to_string(), "C".to_string())]),
+            &questions_db,
+        );
+        let exported = || export_progress(&responses, true, false, None, Some(u64::MAX)).unwrap();
+        let file = progress_file(exported(), "secret").unwrap();
+        let progress = import_progress(&file, &questions_db, "secret").unwrap();
+        assert_eq!(progress, exported());
+        let imported = responses_from_export(&progress, &questions_db).unwrap();
+        assert_eq!(imported["q1_1_0"].correct_answer, Some('C'));
+        assert!(import_progress(&file, &questions_db, "other secret").is_err());
+        let tampered = file.replace("\"C\"", "\"D\"");
+        assert!(import_progress(&tampered, &questions_db, "secret").is_err());
+    }
+
+    #[test]
+    fn test_progress_code_roundtrip() {
+        let test: Test = get_giga_test(&|s| s.to_string());
+        let questions_db = test.get_correct_answers();
+        let responses = responses_from_form_data(
+            &HashMap::from([
+                ("q1_1_0".to_string(), "C".to_string()),
+                ("q6_1_3".to_string(), "A".to_string()),
+            ]),
+            &questions_db,
+        );
+        for shuffle_seed in [None, Some(u64::MAX)] {
+            let progress = export_progress(&responses, false, true, None, shuffle_seed).unwrap();
+            let code = progress_code(&progress, &questions_db, "secret").unwrap();
+            assert_eq!(
+                import_progress(&code, &questions_db, "secret").unwrap(),
+                progress
+            );
+        }
+        let progress = export_progress(&responses, false, true, None, None).unwrap();
+        let code = progress_code(&progress, &questions_db, "secret").unwrap();
+        assert!(import_progress(&code, &questions_db, "other secret").is_err());
+        assert!(import_progress(&code[1..], &questions_db, "secret").is_err());
+    }
+
+    #[test]
+    fn test_timed_attempt_exported_once_finished() {
+        let timer = TimedAttempt::new(
+            TimeLimit {
+                kind: TimeLimitKind::WholeTest,
+                minutes: 60,
+            },
+            0,
+        );
+        let responses = UserResponseData::new();
+        assert!(matches!(
+            export_progress(&responses, false, true, Some(&timer), None),
+            Err(Error::TimedExport)
+        ));
+        assert!(export_progress(&responses, true, true, Some(&timer), None).is_ok());
+    }
+
+    #[test]
+    fn test_untimed_export_version_only_imports_finished() {
+        let questions_db = small_test().get_correct_answers();
+        for (finished, imported) in [(true, true), (false, false)] {
+            let progress = || ExportedProgress {
+                version: EXPORT_VERSION_UNTIMED,
+                finished,
+                ..ExportedProgress::default()
+            };
+            let file = progress_file(progress(), "secret").unwrap();
+            let code = progress_code(&progress(), &questions_db, "secret").unwrap();
+            for input in [file, code] {
+                assert_eq!(
+                    import_progress(&input, &questions_db, "secret").is_ok(),
+                    imported
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_responses_from_export_unknown_question() {
+        let test = small_test();
+        let progress = ExportedProgress {
+            version: EXPORT_VERSION,
+            responses: BTreeMap::from([("q9_9_9".to_string(), 'A')]),
+            finished: false,
+            count_canceled: true,
+            shuffle_seed: None,
+        };
+        assert!(matches!(
+            responses_from_export(&progress, &test.get_correct_answers()),
+            Err(Error::UnknownQuestion(_))
+        ));
+    }
+
+    #[test]
+    fn test_responses_from_rows() {
+        let questions_db = AnswersDB::from([
+            ("q1_1_0".to_string(), Some('B')),
+            ("q1_1_1".to_string(), None),
+        ]);
+        let rows = vec![
+            ("q1_1_0".to_string(), "B".to_string()),
+            ("q1_1_1".to_string(), "C".to_string()),
+            ("q9_9_9".to_string(), "A".to_string()),
+        ];
+        let responses = responses_from_rows(&rows, &questions_db);
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses["q1_1_0"].correct_answer, Some('B'));
+        assert_eq!(responses["q1_1_1"].user_answer, 'C');
+    }
+
+    #[test]
+    fn test_content_hash_follows_correct_answers() {
+        let questions_db = small_test().get_correct_answers();
+        let mut fixed = questions_db.clone();
+        fixed.insert("q1_1_0".to_string(), Some('D'));
+        assert_eq!(
+            content_hash(&questions_db),
+            content_hash(&questions_db.clone())
+        );
+        assert_ne!(content_hash(&questions_db), content_hash(&fixed));
+    }
+
+    #[test]
+    fn test_legacy_responses() {
+        let unversioned: SessionResponses =
+            serde_json::from_str(r#"{ "q1_1_0": { "user_answer": "B", "correct_answer": "A" } }"#)
+                .unwrap();
+        let versioned: SessionResponses = serde_json::from_str(
+            r#"{ "version": 2, "content_hash": "123bd7d1", "answers": { "q1_1_0": "B" } }"#,
+        )
+        .unwrap();
+        let empty: SessionResponses = serde_json::from_str("{}").unwrap();
+        let expected = BTreeMap::from([("q1_1_0".to_string(), 'B')]);
+        assert_eq!(unversioned.into_answers(), expected);
+        assert_eq!(versioned.into_answers(), expected);
+        assert!(empty.into_answers().is_empty());
+    }
+
+    #[test]
+    fn test_regrade_responses() {
+        let questions_db = AnswersDB::from([("q1_1_0".to_string(), None)]);
+        let mut responses = UserResponseData::from([
+            (
+                "q1_1_0".to_string(),
+                UserResponse {
+                    user_answer: 'A',
+                    correct_answer: Some('A'),
+                },
+            ),
+            (
+                "q9_9_9".to_string(),
+                UserResponse {
+                    user_answer: 'A',
+                    correct_answer: Some('A'),
+                },
+            ),
+        ]);
+        regrade_responses(&mut responses, &questions_db);
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses["q1_1_0"].correct_answer, None);
+    }
+
+    #[test]
+    fn test_polish_plural() {
+        let forms = |n| polish_plural(&n, "poprawna", "poprawne", "poprawnych");
+        assert_eq!(forms(1), "poprawna");
+        assert_eq!(forms(2), "poprawne");
+        assert_eq!(forms(5), "poprawnych");
+        assert_eq!(forms(12), "poprawnych");
+        assert_eq!(forms(22), "poprawne");
+        assert_eq!(forms(0), "poprawnych");
+    }
 }
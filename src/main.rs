@@ -1,26 +1,37 @@
 use crate::errors::Error;
-use crate::giga_test::get_giga_test;
+use crate::giga_test::{content_hash, get_giga_test};
+use crate::sqlite::SqliteConfig;
 use axum::Router;
 use include_dir::{include_dir, Dir};
 use regex::Regex;
-use std::fs::File;
-use std::io::ErrorKind;
 use std::process::ExitCode;
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
 use tower_http::compression::CompressionLayer;
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 use tower_serve_static::ServeDir;
 use tower_sessions::{cookie::time::Duration, Expiry, SessionManagerLayer};
-use tower_sessions_sqlx_store::{sqlx::sqlite::SqlitePoolOptions, SqliteStore};
+use tower_sessions_sqlx_store::sqlx::SqlitePool;
 
+mod accounts;
+mod admin;
+mod analytics;
+mod api;
+mod csrf;
+mod db;
 mod env;
 mod errors;
 mod giga_test;
 mod models;
 mod pages;
+mod result_card;
 mod routes;
+mod sessions;
+mod sqlite;
+
+const USAGE: &str = "usage: rust-giga-test-webapp [sessions prune]";
 
 static STATIC_ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/resources");
 
@@ -28,16 +39,19 @@ static STATIC_ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/resou
 pub struct AppState {
     giga_test: models::Test,
     questions_db: models::AnswersDB,
-}
-
-fn ensure_sqlite_file_exists(pool: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let filepath = pool.trim_start_matches("sqlite:");
-    tracing::info!("Ensuring SQLite file exists: {filepath}");
-    match File::create_new(filepath) {
-        Ok(_) => Ok(()),
-        Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(()),
-        Err(e) => Err(Box::new(e)),
-    }
+    db: SqlitePool,
+    admin_password: Option<String>,
+    schedule: Option<models::Schedule>,
+    secret_key: Option<String>,
+    /// See `giga_test::content_hash`
+    content_hash: String,
+    /// Base of absolute URLs; links are relative when `None`
+    public_url: Option<String>,
+    session_store: sessions::SessionBackend,
+    /// Largest request body accepted, see `GIGA_TEST_MAX_BODY_BYTES`
+    max_body_bytes: usize,
+    /// `None` when analytics are turned off
+    analytics_retention_days: Option<u64>,
 }
 
 async fn shutdown_signal() {
@@ -78,44 +92,124 @@ fn html_preprocessor(input: &str) -> String {
     new.to_string()
 }
 
-#[allow(clippy::cognitive_complexity)]
-async fn start() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt::init();
-    let _ = dotenvy::dotenv();
+async fn connect_db(config: &SqliteConfig) -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = config.connect().await?;
+    db::migrate(&pool).await?;
+    Ok(pool)
+}
 
+/// `sessions prune` subcommand, for deleting expired sessions and answers of
+/// their players from cron rather than from the server process
+async fn prune_sessions() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = connect_db(&env::sqlite_config()?).await?;
+    let session_backend = env::session_backend()?;
+    if session_backend == sessions::SessionBackendConfig::Memory {
+        println!("memory session backend is pruned by the server process");
+    } else {
+        let session_store = sessions::SessionBackend::connect(&session_backend, &pool).await?;
+        match session_store.delete_expired().await? {
+            Some(deleted) => println!("deleted {deleted} expired sessions"),
+            None => println!("{session_backend} session backend has nothing to prune"),
+        }
+    }
+    let deleted = db::prune_inactive_players(&pool, env::session_expiry_days()?).await?;
+    println!("deleted answers of {deleted} inactive players");
+    Ok(())
+}
+
+#[allow(clippy::cognitive_complexity)]
+async fn serve() -> Result<(), Box<dyn std::error::Error>> {
     let bind_addr = env::bind_addr()?;
     let timeout = env::http_timeout()?;
-    let sqlite_pool = env::sqlite_pool()?;
-    if sqlite_pool.to_lowercase() != "sqlite::memory:" {
-        ensure_sqlite_file_exists(&sqlite_pool)?;
-    }
+    let max_body_bytes = env::max_body_bytes()?;
+    let sqlite_config = env::sqlite_config()?;
     let giga_test = get_giga_test(&html_preprocessor);
     let questions_db = &giga_test.get_correct_answers().clone();
 
+    let pool = connect_db(&sqlite_config).await?;
+    let session_backend = env::session_backend()?;
+    let session_store = sessions::SessionBackend::connect(&session_backend, &pool).await?;
+    let session_expiry_days = env::session_expiry_days()?;
+    if let Some(period) = env::session_cleanup_interval()? {
+        tokio::spawn(sessions::cleanup_expired(
+            session_store.clone(),
+            pool.clone(),
+            session_expiry_days,
+            period,
+        ));
+    }
+
+    let admin_password = env::admin_password();
+    if admin_password.is_none() {
+        tracing::info!("admin password not set, admin pages are disabled");
+    }
+
+    let secret_key = env::secret_key();
+    if secret_key.is_none() {
+        tracing::info!("secret key not set, progress export is disabled");
+    }
+
+    let public_url = env::public_url();
+    if public_url.is_none() {
+        tracing::info!("public URL not set, Open Graph tags use relative URLs");
+    }
+
+    let analytics_retention_days = env::analytics_retention_days()?;
+    match analytics_retention_days {
+        Some(days) => {
+            tracing::info!("analytics enabled, events are kept for {days} days");
+            tokio::spawn(analytics::prune_old_events(pool.clone(), days));
+        }
+        None => tracing::info!("analytics disabled"),
+    }
+
+    let schedule = env::schedule()?;
+    if schedule.is_some() {
+        tracing::info!("release schedule mode enabled");
+    }
+
     let state = AppState {
         giga_test: giga_test.clone(),
         questions_db: questions_db.clone(),
+        db: pool,
+        admin_password,
+        schedule,
+        secret_key,
+        content_hash: content_hash(questions_db),
+        public_url,
+        session_store: session_store.clone(),
+        max_body_bytes,
+        analytics_retention_days,
     };
 
-    let pool = SqlitePoolOptions::new()
-        .max_connections(1)
-        .connect(&sqlite_pool)
-        .await?;
-    let session_store = SqliteStore::new(pool).with_table_name("sessions")?;
-    session_store.migrate().await?;
-
     tracing::info!("serving on {bind_addr}");
     tracing::info!("timeout set to {timeout:?}");
-    tracing::info!("using SQLite db at {sqlite_pool}");
+    tracing::info!("request bodies limited to {max_body_bytes} bytes");
+    tracing::info!(
+        "using SQLite db at {}, up to {} connections, WAL {}",
+        sqlite_config.url,
+        sqlite_config.pool_size(),
+        if sqlite_config.wal { "on" } else { "off" }
+    );
+    tracing::info!("using {session_backend} session backend");
+    tracing::info!("sessions expire after {session_expiry_days} days of inactivity");
 
-    let cookie_expiry = Expiry::OnInactivity(Duration::new(365 * 24 * 60 * 60, 0));
+    let cookie_expiry = Expiry::OnInactivity(Duration::days(i64::try_from(session_expiry_days)?));
     let service = Router::new()
+        .merge(routes::routes(&state))
+        .merge(api::routes())
+        .merge(accounts::routes(&state))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            accounts::sync_progress,
+        ))
         .nest_service("/static", ServeDir::new(&STATIC_ASSETS_DIR))
-        .merge(routes::routes())
+        .merge(admin::routes(&state))
         .layer(
             ServiceBuilder::new()
                 .layer(CompressionLayer::new())
                 .layer(TraceLayer::new_for_http())
+                .layer(RequestBodyLimitLayer::new(max_body_bytes))
                 .layer(TimeoutLayer::new(timeout))
                 .layer(
                     SessionManagerLayer::new(session_store)
@@ -134,6 +228,23 @@ async fn start() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+async fn start() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+    let _ = dotenvy::dotenv();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => serve().await,
+        ["sessions", "prune"] => prune_sessions().await,
+        _ => Err(USAGE.into()),
+    }
+}
+
 #[tokio::main]
 async fn main() -> ExitCode {
     match start().await {
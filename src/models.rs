@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use utoipa::ToSchema;
 
 fn ret_false() -> bool {
     false
@@ -8,15 +9,29 @@ fn ret_false() -> bool {
 
 pub(crate) type UserResponseData = HashMap<String, UserResponse>;
 pub(crate) type AnswersDB = HashMap<String, Option<char>>;
+pub(crate) type QuestionStatsData = HashMap<String, QuestionStats>;
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
 pub(crate) enum PlaceBucket {
     Winner,
     ConsolationPrize,
     NamePrinted,
     NameWebsite,
+    #[default]
     Loser,
 }
 
+impl PlaceBucket {
+    pub(crate) const ALL: [Self; 5] = [
+        Self::Winner,
+        Self::ConsolationPrize,
+        Self::NamePrinted,
+        Self::NameWebsite,
+        Self::Loser,
+    ];
+}
+
 impl fmt::Display for PlaceBucket {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let display_text = match self {
@@ -30,6 +45,131 @@ impl fmt::Display for PlaceBucket {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+pub(crate) enum LeaderboardPeriod {
+    #[default]
+    #[serde(rename = "wszystko")]
+    AllTime,
+    #[serde(rename = "miesiac")]
+    Month,
+    #[serde(rename = "tydzien")]
+    Week,
+}
+
+impl fmt::Display for LeaderboardPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let display_text = match self {
+            LeaderboardPeriod::AllTime => "wszystko",
+            LeaderboardPeriod::Month => "miesiac",
+            LeaderboardPeriod::Week => "tydzien",
+        };
+        write!(f, "{display_text}")
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub(crate) enum TimeLimitKind {
+    #[serde(rename = "test")]
+    WholeTest,
+    #[serde(rename = "czesc")]
+    PerPart,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub(crate) struct TimeLimit {
+    pub(crate) kind: TimeLimitKind,
+    pub(crate) minutes: u64,
+}
+
+impl fmt::Display for TimeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scope = match self.kind {
+            TimeLimitKind::WholeTest => "cały test",
+            TimeLimitKind::PerPart => "każdą część",
+        };
+        write!(f, "{} min na {scope}", self.minutes)
+    }
+}
+
+/// Time limit of current attempt and when the clock started. With per-part
+/// limit, clock of each part starts when player opens it for the first time.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct TimedAttempt {
+    pub(crate) limit: TimeLimit,
+    pub(crate) started_at: u64,
+    #[serde(default)]
+    pub(crate) parts_started: BTreeMap<String, u64>,
+}
+
+impl TimedAttempt {
+    pub(crate) fn new(limit: TimeLimit, now: u64) -> Self {
+        Self {
+            limit,
+            started_at: now,
+            parts_started: BTreeMap::new(),
+        }
+    }
+
+    /// Unix timestamp after which answers in a part are no longer accepted;
+    /// `None` if clock for that part has not started yet
+    pub(crate) fn deadline(&self, part_id: &str) -> Option<u64> {
+        let started_at = match self.limit.kind {
+            TimeLimitKind::WholeTest => Some(self.started_at),
+            TimeLimitKind::PerPart => self.parts_started.get(part_id).copied(),
+        };
+        started_at.map(|s| s + self.limit.minutes * 60)
+    }
+
+    pub(crate) fn start_part(&mut self, part_id: &str, now: u64) -> bool {
+        if self.limit.kind != TimeLimitKind::PerPart || self.parts_started.contains_key(part_id) {
+            return false;
+        }
+        self.parts_started.insert(part_id.to_string(), now);
+        true
+    }
+}
+
+/// Release schedule for community re-runs - parts unlock one by one, like
+/// they did when the magazine was published
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct Schedule {
+    pub(crate) parts: BTreeMap<String, PartSchedule>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct PartSchedule {
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) unlock: time::OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub(crate) deadline: Option<time::OffsetDateTime>,
+}
+
+impl Schedule {
+    /// Seconds left until part unlocks; `None` if it is already available
+    pub(crate) fn seconds_to_unlock(&self, part_id: &str, now: u64) -> Option<u64> {
+        let unlock = self.parts.get(part_id)?.unlock.unix_timestamp();
+        let unlock = u64::try_from(unlock).unwrap_or_default();
+        (unlock > now).then(|| unlock - now)
+    }
+
+    /// Practice mode reveals correct answers, so it is only available for
+    /// parts that no longer accept answers
+    pub(crate) fn practice_available(&self, part_id: &str, now: u64) -> bool {
+        !self.parts.contains_key(part_id) || self.seconds_to_deadline(part_id, now) == Some(0)
+    }
+
+    /// Seconds left until answers for part close; `None` if there is no
+    /// deadline, `Some(0)` if it has passed
+    pub(crate) fn seconds_to_deadline(&self, part_id: &str, now: u64) -> Option<u64> {
+        let deadline = self.parts.get(part_id)?.deadline?.unix_timestamp();
+        Some(
+            u64::try_from(deadline)
+                .unwrap_or_default()
+                .saturating_sub(now),
+        )
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Default)]
 pub(crate) struct Test(BTreeMap<String, TestPart>);
 
@@ -42,6 +182,19 @@ impl Test {
         self.0.get(key)
     }
 
+    /// Question ID has form `q{part}_{section}_{index}`, see `Section::from_raw`
+    pub(crate) fn get_question(&self, question_id: &str) -> Option<&Question> {
+        let mut ids = question_id.strip_prefix('q')?.split('_');
+        let (part_id, section_id) = (ids.next()?, ids.next()?);
+        self.0
+            .get(part_id)?
+            .sections
+            .get(section_id)?
+            .questions
+            .iter()
+            .find(|question| question.id == question_id)
+    }
+
     pub(crate) fn get_correct_answers(&self) -> AnswersDB {
         self.0
             .values()
@@ -68,8 +221,8 @@ pub(crate) struct TestPart {
 impl TestPart {
     pub(crate) fn get_questions(&self) -> Vec<&Question> {
         self.sections
-            .iter()
-            .flat_map(|(_, section)| section.questions.iter())
+            .values()
+            .flat_map(|section| section.questions.iter())
             .collect()
     }
 }
@@ -150,7 +303,7 @@ impl TestPart {
     }
 }
 
-static QUESTION_IDS: [char; 8] = ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H'];
+pub(crate) static QUESTION_IDS: [char; 8] = ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H'];
 
 impl Section {
     fn from_raw(value: &RawSection, part_id: &str, section_id: &str) -> Self {
@@ -184,6 +337,8 @@ pub(crate) struct TestPartTally {
     total_q: usize,
     answered_good_q: usize,
     answered_bad_q: usize,
+    answered_good_regular_q: usize,
+    answered_good_canceled_q: usize,
 }
 
 impl TestPartTally {
@@ -192,12 +347,16 @@ impl TestPartTally {
         total_q: usize,
         answered_good_q: usize,
         answered_bad_q: usize,
+        answered_good_regular_q: usize,
+        answered_good_canceled_q: usize,
     ) -> Self {
         Self {
             answered_q,
             total_q,
             answered_good_q,
             answered_bad_q,
+            answered_good_regular_q,
+            answered_good_canceled_q,
         }
     }
 }
@@ -208,6 +367,75 @@ pub(crate) struct UserResponse {
     pub(crate) correct_answer: Option<char>,
 }
 
+/// Ungraded answers, for tests
+#[cfg(test)]
+pub(crate) fn responses(answers: &[(&str, char)]) -> UserResponseData {
+    answers
+        .iter()
+        .map(|(question_id, user_answer)| {
+            (
+                (*question_id).to_string(),
+                UserResponse {
+                    user_answer: *user_answer,
+                    correct_answer: None,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Layout of player's rows in `responses` table, stored in `players` table
+/// along with content hash and bumped whenever the layout changes. Versions 1
+/// and 2 were layouts of answers kept in session.
+pub(crate) const RESPONSES_SCHEMA_VERSION: i64 = 3;
+
+/// Separate sets of answers kept for each player in `responses` table
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ResponseKind {
+    Test,
+    Practice,
+    QuickQuiz,
+}
+
+impl ResponseKind {
+    pub(crate) const ALL: [Self; 3] = [Self::Test, Self::Practice, Self::QuickQuiz];
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Test => "test",
+            Self::Practice => "practice",
+            Self::QuickQuiz => "quick_quiz",
+        }
+    }
+}
+
+/// Answers kept in session, in any layout used so far. Older versions kept
+/// them there with any backend, they are moved to `responses` table when
+/// found; see `routes::responses_in_session` for when they still are.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub(crate) enum SessionResponses {
+    /// Picked letters only. Versions 1 and 2 kept schema version and content
+    /// hash next to them, they are ignored now.
+    Versioned { answers: BTreeMap<String, char> },
+    /// Question ID to response, with correct answer copied from the test
+    /// when answer was saved
+    Unversioned(UserResponseData),
+}
+
+impl SessionResponses {
+    pub(crate) fn into_answers(self) -> BTreeMap<String, char> {
+        match self {
+            Self::Versioned { answers } => answers,
+            Self::Unversioned(responses) => responses
+                .into_iter()
+                .map(|(question_id, response)| (question_id, response.user_answer))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct TestStateMainPageElem {
     pub(crate) test_id: String,
     pub(crate) pe_id: String,
@@ -216,6 +444,14 @@ pub(crate) struct TestStateMainPageElem {
     pub(crate) total_q: usize,
     pub(crate) answered_good_q: usize,
     pub(crate) answered_bad_q: usize,
+    /// Correct answers to questions that were not canceled
+    pub(crate) answered_good_regular_q: usize,
+    /// Correct answers to canceled questions, counted even when they are hidden
+    pub(crate) answered_good_canceled_q: usize,
+    /// Set only in release schedule mode
+    pub(crate) seconds_to_unlock: Option<u64>,
+    /// Set only in release schedule mode
+    pub(crate) seconds_to_deadline: Option<u64>,
 }
 
 impl TestStateMainPageElem {
@@ -225,6 +461,8 @@ impl TestStateMainPageElem {
             total_q,
             answered_good_q,
             answered_bad_q,
+            answered_good_regular_q,
+            answered_good_canceled_q,
         } = test_part_tally;
         let (pe_id, pe_date) = match test_id {
             "1" => ("37", "wrzesień 2000"),
@@ -243,19 +481,63 @@ impl TestStateMainPageElem {
             total_q,
             answered_good_q,
             answered_bad_q,
+            answered_good_regular_q,
+            answered_good_canceled_q,
+            seconds_to_unlock: None,
+            seconds_to_deadline: None,
         }
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub(crate) struct TestStateMainPageTotals {
     pub(crate) answered_good_q: usize,
     pub(crate) answered_bad_q: usize,
     pub(crate) answered_total_q: usize,
     pub(crate) total_q: usize,
+    /// Correct answers, canceled questions excluded - what original participants were scored on
+    pub(crate) answered_good_q_without_canceled: usize,
+    /// Correct answers, canceled questions included
+    pub(crate) answered_good_q_with_canceled: usize,
+    /// Place under original competition rules, regardless of canceled questions toggle
     pub(crate) place: usize,
     pub(crate) place_bucket: PlaceBucket,
 }
 
+/// Site-wide statistics of a single question, aggregated from finished attempts
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct QuestionStats {
+    pub(crate) answered: usize,
+    pub(crate) answered_good: usize,
+    pub(crate) picks: BTreeMap<char, usize>,
+}
+
+impl QuestionStats {
+    pub(crate) fn correct_percent(&self) -> usize {
+        if self.answered == 0 {
+            return 0;
+        }
+        (self.answered_good * 100 + self.answered / 2) / self.answered
+    }
+
+    pub(crate) fn pick_percent(&self, choice: char) -> usize {
+        if self.answered == 0 {
+            return 0;
+        }
+        let picks = self.picks.get(&choice).copied().unwrap_or_default();
+        (picks * 100 + self.answered / 2) / self.answered
+    }
+
+    /// Choice picked by most players; ties go to the earlier letter
+    pub(crate) fn most_picked(&self) -> Option<char> {
+        self.picks
+            .iter()
+            .rev()
+            .max_by_key(|(_, picks)| **picks)
+            .map(|(choice, _)| *choice)
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 pub(crate) struct TestStatePartPage {
     pub(crate) introduction: String,
@@ -273,17 +555,252 @@ pub(crate) struct TestStatePartPageQuestion {
     pub(crate) id: String,
     pub(crate) question: String,
     pub(crate) choices: BTreeMap<char, TestStatePartPageAnswerChoice>,
-    #[allow(dead_code)]
     pub(crate) user_answer: Option<char>,
     pub(crate) canceled: bool,
+    pub(crate) stats: Option<QuestionStats>,
+}
+
+impl TestStatePartPageQuestion {
+    pub(crate) fn correct_answer(&self) -> Option<char> {
+        self.choices
+            .iter()
+            .find(|(_, choice)| choice.correct)
+            .map(|(choice_id, _)| *choice_id)
+    }
 }
 
 #[derive(Clone, Debug, Default, Deserialize)]
 pub(crate) struct TestStatePartPageAnswerChoice {
     pub(crate) answer: String,
-    #[allow(dead_code)]
     pub(crate) correct: bool,
     pub(crate) user_selected: bool,
     pub(crate) choice_class: String,
     pub(crate) id: String,
 }
+
+#[derive(Clone, Debug)]
+pub(crate) struct LeaderboardEntry {
+    pub(crate) id: i64,
+    pub(crate) nickname: String,
+    pub(crate) answered_good_q_without_canceled: usize,
+    pub(crate) answered_good_q_with_canceled: usize,
+    pub(crate) place: usize,
+    #[allow(dead_code)]
+    pub(crate) created_at: i64,
+    pub(crate) hidden: bool,
+    pub(crate) timed: bool,
+}
+
+/// What happened in an analytics event
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum EventKind {
+    /// Part page opened while test is in progress
+    PartView,
+    /// First answer to a question
+    Answer,
+    /// Answer replaced with a different letter
+    AnswerChange,
+    Submit,
+    NewTest,
+}
+
+impl EventKind {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::PartView => "part_view",
+            Self::Answer => "answer",
+            Self::AnswerChange => "answer_change",
+            Self::Submit => "submit",
+            Self::NewTest => "new_test",
+        }
+    }
+}
+
+/// Single entry of analytics event log. Holds nothing about the player
+/// apart from pseudonymous visitor ID it's stored under.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Event {
+    pub(crate) kind: EventKind,
+    pub(crate) part_id: Option<String>,
+    pub(crate) question_id: Option<String>,
+}
+
+/// How players take a single part, aggregated from analytics events
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct PartAnalytics {
+    pub(crate) part_id: String,
+    pub(crate) views: usize,
+    /// Average time from opening part page to visitor's next action
+    pub(crate) avg_seconds: Option<u64>,
+    pub(crate) answers: usize,
+    pub(crate) changes: usize,
+    /// Visitors who answered something, but left at this part without
+    /// submitting the test
+    pub(crate) gave_up: usize,
+}
+
+impl PartAnalytics {
+    /// Share of saved answers that replaced a different one
+    pub(crate) fn change_percent(&self) -> usize {
+        let saved = self.answers + self.changes;
+        if saved == 0 {
+            return 0;
+        }
+        (self.changes * 100 + saved / 2) / saved
+    }
+}
+
+/// Analytics totals across the whole test
+#[derive(Debug, Default)]
+pub(crate) struct AnalyticsSummary {
+    pub(crate) visitors: usize,
+    pub(crate) new_tests: usize,
+    pub(crate) submits: usize,
+}
+
+/// Attempts across all players, for admin dashboard
+#[derive(Debug, Default)]
+pub(crate) struct AttemptSummary {
+    /// Attempts with at least one answer, counted since they started to be recorded
+    pub(crate) started: usize,
+    pub(crate) finished: usize,
+    pub(crate) avg_good_q_without_canceled: f64,
+    pub(crate) avg_good_q_with_canceled: f64,
+}
+
+/// Parameters of a quick quiz. The same parameters always draw the same
+/// questions, so a quiz can be resumed or shared as a link.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct QuickQuizDraw {
+    pub(crate) seed: u64,
+    pub(crate) count: usize,
+    /// Draw only from this part, or from all parts when `None`
+    pub(crate) part: Option<String>,
+}
+
+impl QuickQuizDraw {
+    pub(crate) fn url(&self) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query
+            .append_pair("ziarno", &self.seed.to_string())
+            .append_pair("pytan", &self.count.to_string());
+        if let Some(part) = &self.part {
+            query.append_pair("czesc", part);
+        }
+        format!("/szybki-test?{}", query.finish())
+    }
+}
+
+/// Progress exported by player, to be restored in another session. Answers
+/// use canonical letters; correct answers are looked up again on import.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub(crate) struct ExportedProgress {
+    pub(crate) version: u8,
+    pub(crate) responses: BTreeMap<String, char>,
+    pub(crate) finished: bool,
+    pub(crate) count_canceled: bool,
+    /// Left out when choices are in TOML order, so files of older versions
+    /// keep their signatures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) shuffle_seed: Option<u64>,
+}
+
+/// Exported progress file - progress with signature of its JSON form
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct SignedProgress {
+    #[serde(flatten)]
+    pub(crate) progress: ExportedProgress,
+    pub(crate) signature: String,
+}
+
+pub(crate) const RESULT_SNAPSHOT_VERSION: u8 = 1;
+
+/// Immutable snapshot of results, shared under its own URL. Kept as JSON
+/// forever, so it has its own types rather than page view models, and
+/// fields missing from older snapshots get defaults.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct ResultSnapshot {
+    /// 0 for snapshots saved before versioning
+    pub(crate) version: u8,
+    pub(crate) count_canceled: bool,
+    pub(crate) totals: SnapshotTotals,
+    pub(crate) parts: Vec<SnapshotPart>,
+}
+
+impl ResultSnapshot {
+    pub(crate) fn new(
+        count_canceled: bool,
+        totals: &TestStateMainPageTotals,
+        parts: &[TestStateMainPageElem],
+    ) -> Self {
+        Self {
+            version: RESULT_SNAPSHOT_VERSION,
+            count_canceled,
+            totals: SnapshotTotals {
+                answered_good_q: totals.answered_good_q,
+                answered_bad_q: totals.answered_bad_q,
+                answered_total_q: totals.answered_total_q,
+                total_q: totals.total_q,
+                answered_good_q_without_canceled: totals.answered_good_q_without_canceled,
+                answered_good_q_with_canceled: totals.answered_good_q_with_canceled,
+                place: totals.place,
+                place_bucket: totals.place_bucket,
+            },
+            parts: parts
+                .iter()
+                .map(|part| SnapshotPart {
+                    test_id: part.test_id.clone(),
+                    pe_id: part.pe_id.clone(),
+                    pe_date: part.pe_date.clone(),
+                    answered_q: part.answered_q,
+                    total_q: part.total_q,
+                    answered_good_q: part.answered_good_q,
+                    answered_bad_q: part.answered_bad_q,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct SnapshotTotals {
+    pub(crate) answered_good_q: usize,
+    pub(crate) answered_bad_q: usize,
+    pub(crate) answered_total_q: usize,
+    pub(crate) total_q: usize,
+    pub(crate) answered_good_q_without_canceled: usize,
+    pub(crate) answered_good_q_with_canceled: usize,
+    pub(crate) place: usize,
+    pub(crate) place_bucket: PlaceBucket,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct SnapshotPart {
+    pub(crate) test_id: String,
+    pub(crate) pe_id: String,
+    pub(crate) pe_date: String,
+    pub(crate) answered_q: usize,
+    pub(crate) total_q: usize,
+    pub(crate) answered_good_q: usize,
+    pub(crate) answered_bad_q: usize,
+}
+
+/// Finished attempt, archived when player submits the test
+#[derive(Clone, Debug)]
+pub(crate) struct Attempt {
+    pub(crate) id: i64,
+    pub(crate) finished_at: String,
+    pub(crate) answered_good_q: usize,
+    pub(crate) answered_bad_q: usize,
+    pub(crate) total_q: usize,
+    pub(crate) answered_good_q_without_canceled: usize,
+    pub(crate) answered_good_q_with_canceled: usize,
+    pub(crate) place: usize,
+    pub(crate) count_canceled: bool,
+    pub(crate) responses: UserResponseData,
+    pub(crate) time_limit: Option<TimeLimit>,
+    pub(crate) shuffle_seed: Option<u64>,
+}
@@ -1,5 +1,9 @@
+use crate::db::LEADERBOARD_PAGE_SIZE;
+use crate::giga_test::{QUICK_QUIZ_DEFAULT_QUESTIONS, QUICK_QUIZ_MAX_QUESTIONS};
 use crate::models::{
-    PlaceBucket, TestStateMainPageElem, TestStateMainPageTotals, TestStatePartPage,
+    AnalyticsSummary, Attempt, AttemptSummary, LeaderboardEntry, LeaderboardPeriod, PartAnalytics,
+    PlaceBucket, QuestionStats, QuickQuizDraw, ResultSnapshot, TestStateMainPageElem,
+    TestStateMainPageTotals, TestStatePartPage, TimeLimit,
 };
 use askama::Template;
 
@@ -23,20 +27,34 @@ pub struct Index<'a> {
     totals: &'a TestStateMainPageTotals,
     count_canceled: bool,
     giga_test_finished: bool,
+    leaderboard_published: bool,
+    time_limit: Option<&'a TimeLimit>,
+    seconds_left: Option<u64>,
+    /// Set once finished attempt results are saved for sharing
+    result_id: Option<&'a str>,
 }
 
 impl<'a> Index<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         tests_state: &'a [TestStateMainPageElem],
         totals: &'a TestStateMainPageTotals,
         count_canceled: bool,
         giga_test_finished: bool,
+        leaderboard_published: bool,
+        time_limit: Option<&'a TimeLimit>,
+        seconds_left: Option<u64>,
+        result_id: Option<&'a str>,
     ) -> Self {
         Self {
             tests_state,
             totals,
             count_canceled,
             giga_test_finished,
+            leaderboard_published,
+            time_limit,
+            seconds_left,
+            result_id,
         }
     }
 }
@@ -47,13 +65,48 @@ impl<'a> Index<'a> {
 pub struct Part<'a> {
     part_state: &'a TestStatePartPage,
     giga_test_finished: bool,
+    archived_attempt: Option<&'a Attempt>,
+    seconds_left: Option<u64>,
 }
 
 impl<'a> Part<'a> {
-    pub fn new(part_state: &'a TestStatePartPage, giga_test_finished: bool) -> Self {
+    pub fn new(
+        part_state: &'a TestStatePartPage,
+        giga_test_finished: bool,
+        seconds_left: Option<u64>,
+    ) -> Self {
         Self {
             part_state,
             giga_test_finished,
+            archived_attempt: None,
+            seconds_left,
+        }
+    }
+
+    /// Read-only view of a part, as answered in archived attempt
+    pub fn archived(part_state: &'a TestStatePartPage, attempt: &'a Attempt) -> Self {
+        Self {
+            part_state,
+            giga_test_finished: true,
+            archived_attempt: Some(attempt),
+            seconds_left: None,
+        }
+    }
+}
+
+/// Part page shown before part unlocks in release schedule mode
+#[derive(Template)]
+#[template(path = "part_locked.html")]
+pub struct PartLocked {
+    part_id: String,
+    seconds_to_unlock: u64,
+}
+
+impl PartLocked {
+    pub fn new(part_id: String, seconds_to_unlock: u64) -> Self {
+        Self {
+            part_id,
+            seconds_to_unlock,
         }
     }
 }
@@ -68,3 +121,274 @@ impl About {
         Self {}
     }
 }
+
+/// Leaderboard page - results published by site players
+#[derive(Template)]
+#[template(path = "leaderboard.html")]
+pub struct Leaderboard<'a> {
+    entries: &'a [LeaderboardEntry],
+    period: LeaderboardPeriod,
+    count_canceled: bool,
+    timed: bool,
+    /// Counted from 1
+    page: usize,
+    has_next_page: bool,
+}
+
+impl<'a> Leaderboard<'a> {
+    pub fn new(
+        entries: &'a [LeaderboardEntry],
+        period: LeaderboardPeriod,
+        count_canceled: bool,
+        timed: bool,
+        page: usize,
+        has_next_page: bool,
+    ) -> Self {
+        Self {
+            entries,
+            period,
+            count_canceled,
+            timed,
+            page,
+            has_next_page,
+        }
+    }
+
+    /// Position in the whole leaderboard of `index`-th entry on this page
+    fn rank(&self, index: &usize) -> usize {
+        (self.page - 1) * LEADERBOARD_PAGE_SIZE + index
+    }
+}
+
+/// Admin leaderboard page - all entries, with moderation actions
+#[derive(Template)]
+#[template(path = "admin_leaderboard.html")]
+pub struct AdminLeaderboard<'a> {
+    entries: &'a [LeaderboardEntry],
+    /// Counted from 1
+    page: usize,
+    has_next_page: bool,
+}
+
+impl<'a> AdminLeaderboard<'a> {
+    pub fn new(entries: &'a [LeaderboardEntry], page: usize, has_next_page: bool) -> Self {
+        Self {
+            entries,
+            page,
+            has_next_page,
+        }
+    }
+}
+
+/// Admin dashboard - site-wide numbers, with session and leaderboard actions
+#[derive(Template)]
+#[template(path = "admin_dashboard.html")]
+pub struct AdminDashboard<'a> {
+    /// `None` when session backend can't count sessions
+    unexpired_sessions: Option<u64>,
+    summary: &'a AttemptSummary,
+    total_q: usize,
+    place_buckets: &'a [(PlaceBucket, usize)],
+    place_counts: &'a [(usize, usize)],
+    most_missed: &'a [(String, QuestionStats)],
+    recent_entries: &'a [LeaderboardEntry],
+}
+
+impl<'a> AdminDashboard<'a> {
+    pub fn new(
+        unexpired_sessions: Option<u64>,
+        summary: &'a AttemptSummary,
+        total_q: usize,
+        place_buckets: &'a [(PlaceBucket, usize)],
+        place_counts: &'a [(usize, usize)],
+        most_missed: &'a [(String, QuestionStats)],
+        recent_entries: &'a [LeaderboardEntry],
+    ) -> Self {
+        Self {
+            unexpired_sessions,
+            summary,
+            total_q,
+            place_buckets,
+            place_counts,
+            most_missed,
+            recent_entries,
+        }
+    }
+}
+
+/// Admin analytics page - how players take the test, part by part
+#[derive(Template)]
+#[template(path = "admin_analytics.html")]
+pub struct AdminAnalytics<'a> {
+    /// `None` when analytics are turned off
+    retention_days: Option<u64>,
+    summary: &'a AnalyticsSummary,
+    parts: &'a [PartAnalytics],
+}
+
+impl<'a> AdminAnalytics<'a> {
+    pub fn new(
+        retention_days: Option<u64>,
+        summary: &'a AnalyticsSummary,
+        parts: &'a [PartAnalytics],
+    ) -> Self {
+        Self {
+            retention_days,
+            summary,
+            parts,
+        }
+    }
+}
+
+/// History page - list of finished attempts
+#[derive(Template)]
+#[template(path = "history.html")]
+pub struct History<'a> {
+    attempts: &'a [(Attempt, Option<isize>)],
+}
+
+impl<'a> History<'a> {
+    pub fn new(attempts: &'a [(Attempt, Option<isize>)]) -> Self {
+        Self { attempts }
+    }
+}
+
+/// Archived attempt page - summary and list of parts
+#[derive(Template)]
+#[template(path = "attempt.html")]
+pub struct ArchivedAttempt<'a> {
+    attempt: &'a Attempt,
+    tests_state: &'a [TestStateMainPageElem],
+}
+
+impl<'a> ArchivedAttempt<'a> {
+    pub fn new(attempt: &'a Attempt, tests_state: &'a [TestStateMainPageElem]) -> Self {
+        Self {
+            attempt,
+            tests_state,
+        }
+    }
+}
+
+/// Practice index page - list of parts with practice answers tally
+#[derive(Template)]
+#[template(path = "practice_index.html")]
+pub struct PracticeIndex<'a> {
+    tests_state: &'a [TestStateMainPageElem],
+}
+
+impl<'a> PracticeIndex<'a> {
+    pub fn new(tests_state: &'a [TestStateMainPageElem]) -> Self {
+        Self { tests_state }
+    }
+}
+
+/// Practice part page - questions with immediate feedback
+#[derive(Template)]
+#[template(path = "practice_part.html")]
+pub struct PracticePart<'a> {
+    part_state: &'a TestStatePartPage,
+}
+
+impl<'a> PracticePart<'a> {
+    pub fn new(part_state: &'a TestStatePartPage) -> Self {
+        Self { part_state }
+    }
+}
+
+/// Quick quiz start page - number of questions and part to draw from
+#[derive(Template)]
+#[template(path = "quick_quiz_new.html")]
+pub struct QuickQuizNew<'a> {
+    parts: &'a [&'a str],
+    default_count: usize,
+    max_count: usize,
+}
+
+impl<'a> QuickQuizNew<'a> {
+    pub fn new(parts: &'a [&'a str]) -> Self {
+        Self {
+            parts,
+            default_count: QUICK_QUIZ_DEFAULT_QUESTIONS,
+            max_count: QUICK_QUIZ_MAX_QUESTIONS,
+        }
+    }
+}
+
+/// Quick quiz page - drawn questions on one page, with results once checked
+#[derive(Template)]
+#[template(path = "quick_quiz.html")]
+pub struct QuickQuiz<'a> {
+    part_state: &'a TestStatePartPage,
+    draw: &'a QuickQuizDraw,
+    tally: &'a TestStateMainPageElem,
+    finished: bool,
+    parts: &'a [&'a str],
+    default_count: usize,
+    max_count: usize,
+}
+
+impl<'a> QuickQuiz<'a> {
+    pub fn new(
+        part_state: &'a TestStatePartPage,
+        draw: &'a QuickQuizDraw,
+        tally: &'a TestStateMainPageElem,
+        finished: bool,
+        parts: &'a [&'a str],
+    ) -> Self {
+        Self {
+            part_state,
+            draw,
+            tally,
+            finished,
+            parts,
+            default_count: QUICK_QUIZ_DEFAULT_QUESTIONS,
+            max_count: QUICK_QUIZ_MAX_QUESTIONS,
+        }
+    }
+}
+
+/// Account page - login and registration, or account details
+#[derive(Template)]
+#[template(path = "account.html")]
+pub struct Account<'a> {
+    username: Option<&'a str>,
+}
+
+impl<'a> Account<'a> {
+    pub fn new(username: Option<&'a str>) -> Self {
+        Self { username }
+    }
+}
+
+/// Export page - code and file with progress, and import form
+#[derive(Template)]
+#[template(path = "export.html")]
+pub struct Export<'a> {
+    code: &'a str,
+}
+
+impl<'a> Export<'a> {
+    pub fn new(code: &'a str) -> Self {
+        Self { code }
+    }
+}
+
+/// Shared result page - snapshot of finished attempt, with Open Graph tags
+#[derive(Template)]
+#[template(path = "shared_result.html")]
+pub struct SharedResult<'a> {
+    id: &'a str,
+    snapshot: &'a ResultSnapshot,
+    base_url: &'a str,
+}
+
+impl<'a> SharedResult<'a> {
+    pub fn new(id: &'a str, snapshot: &'a ResultSnapshot, base_url: &'a str) -> Self {
+        Self {
+            id,
+            snapshot,
+            base_url,
+        }
+    }
+}
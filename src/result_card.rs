@@ -0,0 +1,197 @@
+use crate::giga_test::polish_plural;
+use crate::models::{PlaceBucket, ResultSnapshot};
+use crate::Error;
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use tiny_skia::{Color, FillRule, Mask, Paint, PathBuilder, Pixmap, Rect, Transform};
+
+// Size recommended for Open Graph images
+const CARD_WIDTH: u32 = 1200;
+const CARD_HEIGHT: u32 = 630;
+const CARD_MARGIN: f32 = 80.0;
+
+static FONT: &[u8] = include_bytes!("../fonts/DejaVuSans-Bold.ttf");
+
+fn color(hex: u32) -> Color {
+    let [_, r, g, b] = hex.to_be_bytes();
+    Color::from_rgba8(r, g, b, 255)
+}
+
+fn paint(hex: u32) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(color(hex));
+    paint.anti_alias = true;
+    paint
+}
+
+fn text_width(font: &FontRef, scale: f32, text: &str) -> f32 {
+    let font = font.as_scaled(PxScale::from(scale));
+    let mut previous = None;
+    let mut width = 0.0;
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, id);
+        }
+        width += font.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Draw single line of text, horizontally centered, with baseline at `y`
+fn draw_text(pixmap: &mut Pixmap, font: &FontRef, scale: f32, y: f32, text: &str, hex: u32) {
+    let Some(mut mask) = Mask::new(pixmap.width(), pixmap.height()) else {
+        return;
+    };
+    let scaled = font.as_scaled(PxScale::from(scale));
+    let width = pixmap.width();
+    let mut x = (width as f32 - text_width(font, scale, text)) / 2.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            x += scaled.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(scale, ab_glyph::point(x, y));
+        x += scaled.h_advance(id);
+        previous = Some(id);
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        let data = mask.data_mut();
+        outline.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + i64::from(gx);
+            let py = bounds.min.y as i64 + i64::from(gy);
+            let (Ok(px), Ok(py)) = (u32::try_from(px), u32::try_from(py)) else {
+                return;
+            };
+            if px >= width || py >= CARD_HEIGHT {
+                return;
+            }
+            let index = (py * width + px) as usize;
+            if let Some(value) = data.get_mut(index) {
+                let coverage = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+                *value = (*value).max(coverage);
+            }
+        });
+    }
+    if let Some(rect) = Rect::from_xywh(0.0, 0.0, width as f32, pixmap.height() as f32) {
+        pixmap.fill_rect(rect, &paint(hex), Transform::identity(), Some(&mask));
+    }
+}
+
+fn fill_rounded_rect(pixmap: &mut Pixmap, x: f32, y: f32, w: f32, h: f32, hex: u32) {
+    let r = (h / 2.0).min(w / 2.0);
+    let mut path = PathBuilder::new();
+    path.move_to(x + r, y);
+    path.line_to(x + w - r, y);
+    path.quad_to(x + w, y, x + w, y + r);
+    path.line_to(x + w, y + h - r);
+    path.quad_to(x + w, y + h, x + w - r, y + h);
+    path.line_to(x + r, y + h);
+    path.quad_to(x, y + h, x, y + h - r);
+    path.line_to(x, y + r);
+    path.quad_to(x, y, x + r, y);
+    path.close();
+    if let Some(path) = path.finish() {
+        pixmap.fill_path(
+            &path,
+            &paint(hex),
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+    }
+}
+
+fn place_bucket_text(place_bucket: &PlaceBucket) -> (&'static str, u32) {
+    match place_bucket {
+        PlaceBucket::Winner => ("Superkonsola PlayStation 2!", 0x00f5_c542),
+        PlaceBucket::ConsolationPrize => ("Nagroda pocieszenia - pad", 0x00c0_c7d0),
+        PlaceBucket::NamePrinted => ("Nazwisko w czasopiśmie", 0x00d9_9a5b),
+        PlaceBucket::NameWebsite => ("Nazwisko na stronie internetowej", 0x008e_c5ff),
+        PlaceBucket::Loser => ("Bez nagrody, ale liczy się zabawa", 0x00a0_a8b8),
+    }
+}
+
+/// PNG card with score and place, used as Open Graph image of a result.
+/// Drawn locally with embedded DejaVu Sans font.
+pub(crate) fn render_result_card(snapshot: &ResultSnapshot) -> Result<Vec<u8>, Error> {
+    let mut pixmap = Pixmap::new(CARD_WIDTH, CARD_HEIGHT)
+        .ok_or_else(|| Error::ImageRender("could not allocate image".to_string()))?;
+    pixmap.fill(color(0x0014_1a26));
+    let font = FontRef::try_from_slice(FONT).map_err(|e| Error::ImageRender(e.to_string()))?;
+    let totals = &snapshot.totals;
+    let (bucket_text, bucket_color) = place_bucket_text(&totals.place_bucket);
+
+    draw_text(
+        &mut pixmap,
+        &font,
+        44.0,
+        120.0,
+        "PSX Extreme Giga Test",
+        0x00a0_a8b8,
+    );
+    draw_text(
+        &mut pixmap,
+        &font,
+        104.0,
+        260.0,
+        &format!(
+            "{} {}",
+            totals.answered_good_q_without_canceled,
+            polish_plural(
+                &totals.answered_good_q_without_canceled,
+                "poprawna",
+                "poprawne",
+                "poprawnych"
+            )
+        ),
+        0x00ff_ffff,
+    );
+    draw_text(
+        &mut pixmap,
+        &font,
+        72.0,
+        360.0,
+        &format!("{}. miejsce", totals.place),
+        bucket_color,
+    );
+    draw_text(&mut pixmap, &font, 36.0, 430.0, bucket_text, bucket_color);
+
+    // One bar per part, filled with share of correct answers
+    let parts = snapshot.parts.len().max(1) as f32;
+    let gap = 24.0;
+    let bar_width = (CARD_WIDTH as f32 - 2.0 * CARD_MARGIN - gap * (parts - 1.0)) / parts;
+    for (index, part) in snapshot.parts.iter().enumerate() {
+        let x = CARD_MARGIN + index as f32 * (bar_width + gap);
+        fill_rounded_rect(&mut pixmap, x, 510.0, bar_width, 24.0, 0x002a_3345);
+        if part.total_q > 0 && part.answered_good_q > 0 {
+            let share = part.answered_good_q as f32 / part.total_q as f32;
+            let width = (bar_width * share.min(1.0)).max(24.0);
+            fill_rounded_rect(&mut pixmap, x, 510.0, width, 24.0, bucket_color);
+        }
+    }
+
+    pixmap
+        .encode_png()
+        .map_err(|e| Error::ImageRender(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::giga_test::{get_giga_test, get_index_tests_state, get_index_totals};
+    use crate::models::{Test, UserResponseData};
+
+    #[test]
+    fn test_render_result_card() {
+        let test: Test = get_giga_test(&|s| s.to_string());
+        let parts = get_index_tests_state(&test, &UserResponseData::new(), true);
+        let snapshot = ResultSnapshot::new(true, &get_index_totals(&parts), &parts);
+        let png = render_result_card(&snapshot).unwrap();
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (CARD_WIDTH, CARD_HEIGHT));
+    }
+}
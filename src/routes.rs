@@ -1,22 +1,86 @@
+use crate::analytics;
+use crate::csrf;
+use crate::db;
 use crate::giga_test::{
-    get_index_tests_state, get_index_totals, get_part_state, responses_from_form_data,
+    apply_schedule, attempts_progress, canonical_form_data, draw_quick_quiz, export_progress,
+    get_index_tests_state, get_index_totals, get_part_state, import_progress, progress_code,
+    progress_file, question_part_id, question_stats_from_rows, regrade_responses,
+    responses_from_export, responses_from_form_data, responses_from_rows, tally_quick_quiz,
+    unix_now, validate_form_data, validate_nickname, QUICK_QUIZ_DEFAULT_QUESTIONS,
+    QUICK_QUIZ_MAX_QUESTIONS,
 };
-use crate::models::UserResponseData;
-use crate::pages::{About, ErrorPage, Index, Part};
+use crate::models::{
+    Event, EventKind, ExportedProgress, LeaderboardPeriod, QuestionStatsData, QuickQuizDraw,
+    ResponseKind, ResultSnapshot, SessionResponses, TestStateMainPageElem, TestStateMainPageTotals,
+    TestStatePartPage, TimeLimit, TimeLimitKind, TimedAttempt, UserResponseData,
+};
+use crate::pages::{
+    About, ArchivedAttempt, ErrorPage, Export, History, Index, Leaderboard, Part, PartLocked,
+    PracticeIndex, PracticePart, QuickQuiz, QuickQuizNew, SharedResult,
+};
+use crate::result_card::render_result_card;
 use crate::AppState;
 use crate::Error;
 use askama::Template;
-use axum::extract::{Form, Path, State};
+use axum::extract::multipart::MultipartError;
+use axum::extract::rejection::FormRejection;
+use axum::extract::{Form, Multipart, Path, Query, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE};
 use axum::http::StatusCode;
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::routing::{get, post, Router};
+use axum::Json;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 use tower_sessions::Session;
 
-const GT_RESP_KEY: &str = "giga_test_responses";
-const GT_FINISHED_KEY: &str = "giga_test_finished";
+pub(crate) const GT_FINISHED_KEY: &str = "giga_test_finished";
 const GT_COUNT_CANCELED_KEY: &str = "giga_test_count_canceled";
+const GT_PUBLISHED_KEY: &str = "giga_test_leaderboard_published";
+pub(crate) const GT_PLAYER_KEY: &str = "giga_test_player_id";
+pub(crate) const GT_TIMER_KEY: &str = "giga_test_timer";
+// Seed for shuffling answer choices, absent when choices are in TOML order
+const GT_SHUFFLE_KEY: &str = "giga_test_shuffle_seed";
+const GT_QUICK_QUIZ_KEY: &str = "giga_test_quick_quiz";
+const GT_QUICK_QUIZ_FINISHED_KEY: &str = "giga_test_quick_quiz_finished";
+// ID of shareable snapshot of finished attempt results
+const GT_RESULT_KEY: &str = "giga_test_result_id";
+// Set when an attempt is restarted or imported, absent for player's first one
+const GT_ATTEMPT_KEY: &str = "giga_test_attempt_id";
+
+/// Session entries that make up player progress. For logged in players they
+/// are mirrored in the account, see `accounts::sync_progress`. Answers are
+/// kept in `responses` table instead, under player ID, unless they are kept in
+/// session too, see `responses_in_session`.
+static PROGRESS_KEYS: &[&str] = &[
+    GT_FINISHED_KEY,
+    GT_COUNT_CANCELED_KEY,
+    GT_PUBLISHED_KEY,
+    GT_TIMER_KEY,
+    GT_SHUFFLE_KEY,
+    GT_QUICK_QUIZ_KEY,
+    GT_QUICK_QUIZ_FINISHED_KEY,
+    GT_RESULT_KEY,
+    GT_ATTEMPT_KEY,
+];
+
+/// Session (and account progress) entries with answers, when they are kept in
+/// session, see `responses_in_session`. Older versions always kept them
+/// there - with SQLite backend they are moved to `responses` table when found.
+pub(crate) static SESSION_RESPONSE_KEYS: &[(&str, ResponseKind)] = &[
+    ("giga_test_responses", ResponseKind::Test),
+    ("giga_test_practice_responses", ResponseKind::Practice),
+    ("giga_test_quick_quiz_responses", ResponseKind::QuickQuiz),
+];
+
+// Answers arriving shortly after deadline are still accepted - countdown
+// script submits the form when time is up, and request takes a moment
+const TIME_LIMIT_GRACE_SECS: u64 = 15;
+const TIME_LIMIT_MAX_MINUTES: u64 = 600;
+const RESULT_ID_LEN: usize = 12;
+/// Form field naming the question whose answer is removed
+const CLEAR_ANSWER_FIELD: &str = "wyczysc";
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct CountCanceled(bool);
@@ -30,19 +94,386 @@ impl Default for CountCanceled {
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 struct TestFinished(bool);
 
-impl IntoResponse for Error {
-    fn into_response(self) -> Response {
-        let status = match &self {
-            Self::Render(_) => StatusCode::INTERNAL_SERVER_ERROR,
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct LeaderboardPublished(bool);
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardQuery {
+    #[serde(default)]
+    okres: LeaderboardPeriod,
+    anulowane: Option<bool>,
+    #[serde(default)]
+    na_czas: bool,
+    /// Counted from 1
+    strona: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PracticeAnswerForm {
+    sprawdz: String,
+    #[serde(flatten)]
+    answers: HashMap<String, String>,
+}
+
+/// Answer saved by autosave script, with letter as displayed on the page.
+/// No letter when answer was cleared.
+#[derive(Debug, Serialize)]
+struct SavedAnswer {
+    question_id: String,
+    answer: Option<char>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuickQuizQuery {
+    ziarno: Option<u64>,
+    pytan: Option<usize>,
+    czesc: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuickQuizForm {
+    pytan: usize,
+    #[serde(default)]
+    czesc: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewTestForm {
+    #[serde(default)]
+    limit: String,
+    /// Only read when a limit is picked, browsers send it empty otherwise
+    #[serde(default)]
+    minuty: String,
+    #[serde(default)]
+    tasuj: bool,
+}
+
+impl NewTestForm {
+    fn time_limit(&self) -> Result<Option<TimeLimit>, Error> {
+        let kind = match self.limit.as_str() {
+            "" => return Ok(None),
+            "test" => TimeLimitKind::WholeTest,
+            "czesc" => TimeLimitKind::PerPart,
+            _ => return Err(Error::IllegalCharacters),
+        };
+        let minutes = self.minuty.parse().map_err(|_| Error::WrongSize)?;
+        check_time_limit(TimeLimit { kind, minutes }).map(Some)
+    }
+}
+
+pub(crate) fn check_time_limit(limit: TimeLimit) -> Result<TimeLimit, Error> {
+    if !(1..=TIME_LIMIT_MAX_MINUTES).contains(&limit.minutes) {
+        return Err(Error::WrongSize);
+    }
+    Ok(limit)
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardForm {
+    nickname: String,
+}
+
+pub(crate) async fn get_count_canceled(session: &Session) -> bool {
+    let count_canceled: CountCanceled = session
+        .get(GT_COUNT_CANCELED_KEY)
+        .await
+        .unwrap_or_default()
+        .unwrap_or_default();
+    count_canceled.0
+}
+
+pub(crate) async fn get_test_finished(session: &Session) -> bool {
+    let test_finished: TestFinished = session
+        .get(GT_FINISHED_KEY)
+        .await
+        .unwrap_or_default()
+        .unwrap_or_default();
+    test_finished.0
+}
+
+async fn get_shuffle_seed(session: &Session) -> Option<u64> {
+    session.get(GT_SHUFFLE_KEY).await.unwrap_or_default()
+}
+
+async fn new_attempt_id(session: &Session) {
+    session
+        .insert(GT_ATTEMPT_KEY, uuid::Uuid::new_v4().simple().to_string())
+        .await
+        .unwrap_or_default();
+}
+
+/// Random identifier of a player, created on first use. Archived attempts are
+/// stored under it, so they outlive responses kept in session.
+pub(crate) async fn get_player_id(session: &Session) -> String {
+    let player_id: Option<String> = session.get(GT_PLAYER_KEY).await.unwrap_or_default();
+    if let Some(player_id) = player_id {
+        return player_id;
+    }
+    let player_id = uuid::Uuid::new_v4().to_string();
+    session
+        .insert(GT_PLAYER_KEY, &player_id)
+        .await
+        .unwrap_or_default();
+    player_id
+}
+
+/// Move answers kept in session or account progress by older versions to
+/// `responses` table. Answers already in the table win.
+pub(crate) async fn save_legacy_responses(
+    state: &AppState,
+    player_id: &str,
+    kind: ResponseKind,
+    value: Value,
+) -> Result<(), Error> {
+    let Ok(legacy) = serde_json::from_value::<SessionResponses>(value) else {
+        return Ok(());
+    };
+    db::insert_missing_responses(&state.db, player_id, kind, &legacy.into_answers()).await?;
+    Ok(())
+}
+
+pub(crate) async fn migrate_session_responses(
+    session: &Session,
+    state: &AppState,
+) -> Result<(), Error> {
+    if responses_in_session(state) {
+        return Ok(());
+    }
+    for (key, kind) in SESSION_RESPONSE_KEYS {
+        if let Ok(Some(value)) = session.get::<Value>(key).await {
+            let player_id = get_player_id(session).await;
+            save_legacy_responses(state, &player_id, *kind, value).await?;
+            let _ = session.remove::<Value>(key).await;
+        }
+    }
+    Ok(())
+}
+
+/// Answers are kept in `responses` table only when sessions are in the same
+/// SQLite file. Other backends are there so replicas can share sessions, while
+/// each replica has its own SQLite file - answers stay in session then, so they
+/// follow the player to whichever replica serves them. Two tabs saving at once
+/// can overwrite each other's answers there, like sessions in general.
+pub(crate) fn responses_in_session(state: &AppState) -> bool {
+    !state.session_store.is_sqlite()
+}
+
+/// Session entries to mirror in account progress
+pub(crate) fn progress_keys(state: &AppState) -> Vec<&'static str> {
+    let mut keys = PROGRESS_KEYS.to_vec();
+    if responses_in_session(state) {
+        keys.extend(SESSION_RESPONSE_KEYS.iter().map(|(key, _)| *key));
+    }
+    keys
+}
+
+fn session_response_key(kind: ResponseKind) -> &'static str {
+    SESSION_RESPONSE_KEYS
+        .iter()
+        .find(|(_, k)| *k == kind)
+        .map(|(key, _)| *key)
+        .unwrap_or_default()
+}
+
+/// Answers of given kind kept in session, only to questions still in the test
+async fn session_answers(
+    session: &Session,
+    state: &AppState,
+    kind: ResponseKind,
+) -> BTreeMap<String, char> {
+    let responses: Option<SessionResponses> = session
+        .get(session_response_key(kind))
+        .await
+        .unwrap_or_default();
+    let mut answers = responses
+        .map(SessionResponses::into_answers)
+        .unwrap_or_default();
+    answers.retain(|question_id, _| state.questions_db.contains_key(question_id));
+    answers
+}
+
+async fn set_session_answers(
+    session: &Session,
+    kind: ResponseKind,
+    answers: BTreeMap<String, char>,
+) {
+    let key = session_response_key(kind);
+    if answers.is_empty() {
+        let _ = session.remove::<Value>(key).await;
+    } else {
+        session
+            .insert(key, SessionResponses::Versioned { answers })
+            .await
+            .unwrap_or_default();
+    }
+}
+
+/// Mark player as seen, and once test content changes, drop their answers to
+/// questions that are no longer in the test
+async fn touch_player(state: &AppState, player_id: &str) -> Result<(), Error> {
+    if !db::touch_player(&state.db, player_id, &state.content_hash).await? {
+        return Ok(());
+    }
+    for kind in ResponseKind::ALL {
+        for (question_id, _) in db::get_responses(&state.db, player_id, kind).await? {
+            if !state.questions_db.contains_key(&question_id) {
+                db::delete_response(&state.db, player_id, kind, &question_id).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Player's answers of given kind, graded against current test
+async fn get_responses(
+    session: &Session,
+    state: &AppState,
+    kind: ResponseKind,
+) -> Result<UserResponseData, Error> {
+    migrate_session_responses(session, state).await?;
+    // Visitors who haven't answered anything don't get player ID yet
+    let player_id: Option<String> = session.get(GT_PLAYER_KEY).await.unwrap_or_default();
+    let Some(player_id) = player_id else {
+        return Ok(UserResponseData::new());
+    };
+    touch_player(state, &player_id).await?;
+    let rows = if responses_in_session(state) {
+        session_answers(session, state, kind)
+            .await
+            .into_iter()
+            .map(|(question_id, answer)| (question_id, answer.to_string()))
+            .collect()
+    } else {
+        db::get_responses(&state.db, &player_id, kind).await?
+    };
+    Ok(responses_from_rows(&rows, &state.questions_db))
+}
+
+async fn save_responses(
+    session: &Session,
+    state: &AppState,
+    kind: ResponseKind,
+    responses: &UserResponseData,
+) -> Result<(), Error> {
+    if responses.is_empty() {
+        return Ok(());
+    }
+    migrate_session_responses(session, state).await?;
+    let player_id = get_player_id(session).await;
+    touch_player(state, &player_id).await?;
+    if responses_in_session(state) {
+        let mut answers = session_answers(session, state, kind).await;
+        // Table has no answers of this player, so the start is recorded when
+        // the first answer of an attempt is saved, same as with the table
+        if kind == ResponseKind::Test && answers.is_empty() {
+            db::record_attempt_start(&state.db, &player_id).await?;
+        }
+        answers.extend(
+            responses
+                .iter()
+                .map(|(question_id, response)| (question_id.clone(), response.user_answer)),
+        );
+        set_session_answers(session, kind, answers).await;
+        return Ok(());
+    }
+    if kind == ResponseKind::Test {
+        db::record_attempt_start(&state.db, &player_id).await?;
+    }
+    db::upsert_responses(&state.db, &player_id, kind, responses).await?;
+    Ok(())
+}
+
+async fn replace_responses(
+    session: &Session,
+    state: &AppState,
+    kind: ResponseKind,
+    responses: &UserResponseData,
+) -> Result<(), Error> {
+    if responses_in_session(state) {
+        let answers = responses
+            .iter()
+            .map(|(question_id, response)| (question_id.clone(), response.user_answer))
+            .collect();
+        set_session_answers(session, kind, answers).await;
+        return Ok(());
+    }
+    let player_id = get_player_id(session).await;
+    touch_player(state, &player_id).await?;
+    db::replace_responses(&state.db, &player_id, kind, responses).await?;
+    Ok(())
+}
+
+async fn delete_response(
+    session: &Session,
+    state: &AppState,
+    kind: ResponseKind,
+    question_id: &str,
+) -> Result<(), Error> {
+    if responses_in_session(state) {
+        let mut answers = session_answers(session, state, kind).await;
+        answers.remove(question_id);
+        set_session_answers(session, kind, answers).await;
+        return Ok(());
+    }
+    let player_id: Option<String> = session.get(GT_PLAYER_KEY).await.unwrap_or_default();
+    if let Some(player_id) = player_id {
+        db::delete_response(&state.db, &player_id, kind, question_id).await?;
+    }
+    Ok(())
+}
+
+async fn clear_responses(
+    session: &Session,
+    state: &AppState,
+    kind: ResponseKind,
+) -> Result<(), Error> {
+    if responses_in_session(state) {
+        set_session_answers(session, kind, BTreeMap::new()).await;
+        return Ok(());
+    }
+    migrate_session_responses(session, state).await?;
+    let player_id: Option<String> = session.get(GT_PLAYER_KEY).await.unwrap_or_default();
+    if let Some(player_id) = player_id {
+        db::clear_responses(&state.db, &player_id, kind).await?;
+    }
+    Ok(())
+}
+
+impl Error {
+    pub(crate) fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Render(_) | Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::NotFound => StatusCode::NOT_FOUND,
             Self::IllegalCharacters
             | Self::IntConversion(_)
             | Self::WrongSize
-            | Self::CookieParsing(_) => StatusCode::BAD_REQUEST,
-            Self::Join(_) | Self::Compression(_) | Self::Axum(_) => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
-        };
+            | Self::CookieParsing(_)
+            | Self::NicknameNotAllowed
+            | Self::TestNotFinished
+            | Self::UsernameTaken
+            | Self::InvalidSignature
+            | Self::OutdatedExport
+            | Self::UnknownQuestion(_)
+            | Self::InvalidJson(_) => StatusCode::BAD_REQUEST,
+            Self::TimeExpired
+            | Self::PartLocked
+            | Self::WrongCredentials
+            | Self::CsrfTokenMismatch
+            | Self::TimedExport
+            | Self::TestFinished => StatusCode::FORBIDDEN,
+            Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Join(_)
+            | Self::Compression(_)
+            | Self::Axum(_)
+            | Self::PasswordHash(_)
+            | Self::ImageRender(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
         ErrorPage::new(self.to_string()).render().map_or_else(
             |_| (status, "Something went wrong").into_response(),
             |body| (status, Html(body)).into_response(),
@@ -50,138 +481,1047 @@ impl IntoResponse for Error {
     }
 }
 
+/// State of every part, with schedule applied, and totals across the test
+pub(crate) async fn load_index(
+    state: &AppState,
+    session: &Session,
+) -> Result<(Vec<TestStateMainPageElem>, TestStateMainPageTotals), Error> {
+    let test_responses = get_responses(session, state, ResponseKind::Test).await?;
+    let count_canceled = get_count_canceled(session).await;
+    let mut index_tests_state =
+        get_index_tests_state(&state.giga_test, &test_responses, count_canceled);
+    if let Some(schedule) = &state.schedule {
+        apply_schedule(&mut index_tests_state, schedule, unix_now());
+    }
+    let totals = get_index_totals(&index_tests_state);
+    Ok((index_tests_state, totals))
+}
+
 async fn get_index(
     State(state): State<AppState>,
     session: Session,
 ) -> Result<impl IntoResponse, Error> {
-    let test_responses: UserResponseData = session
-        .get(GT_RESP_KEY)
-        .await
-        .unwrap_or_default()
-        .unwrap_or_default();
-    let count_canceled: CountCanceled = session
-        .get(GT_COUNT_CANCELED_KEY)
+    let (index_tests_state, totals) = load_index(&state, &session).await?;
+    let count_canceled = get_count_canceled(&session).await;
+    let test_finished: TestFinished = session
+        .get(GT_FINISHED_KEY)
         .await
         .unwrap_or_default()
         .unwrap_or_default();
-    let test_finished: TestFinished = session
-        .get(GT_FINISHED_KEY)
+    let leaderboard_published: LeaderboardPublished = session
+        .get(GT_PUBLISHED_KEY)
         .await
         .unwrap_or_default()
         .unwrap_or_default();
-    let index_tests_state =
-        get_index_tests_state(&state.giga_test, &test_responses, count_canceled.0);
-    let totals = get_index_totals(&index_tests_state);
+    let timer: Option<TimedAttempt> = session.get(GT_TIMER_KEY).await.unwrap_or_default();
+    let seconds_left = timer
+        .as_ref()
+        .filter(|t| t.limit.kind == TimeLimitKind::WholeTest)
+        .and_then(|t| t.deadline(""))
+        .map(|deadline| deadline.saturating_sub(unix_now()));
+    let result_id: Option<String> = session.get(GT_RESULT_KEY).await.unwrap_or_default();
     Ok(Html(
         Index::new(
             &index_tests_state,
             &totals,
-            count_canceled.0,
+            count_canceled,
             test_finished.0,
+            leaderboard_published.0,
+            timer.as_ref().map(|t| &t.limit),
+            seconds_left,
+            result_id.as_deref(),
         )
         .render()?,
     ))
 }
 
+/// Part page contents, or time left until part unlocks
+pub(crate) enum PartView {
+    Locked(u64),
+    Open {
+        part_state: TestStatePartPage,
+        test_finished: bool,
+        seconds_left: Option<u64>,
+    },
+}
+
+/// Part of the test as player sees it. Opening a part starts its timer in
+/// per-part timed attempts. Choices are in canonical order, unless
+/// `shuffle` is set and player asked for shuffled ones.
+pub(crate) async fn load_part(
+    state: &AppState,
+    session: &Session,
+    test_id: &str,
+    shuffle: bool,
+) -> Result<PartView, Error> {
+    let test_part = state.giga_test.get(test_id).ok_or(Error::NotFound)?;
+    if let Some(seconds_to_unlock) = state
+        .schedule
+        .as_ref()
+        .and_then(|schedule| schedule.seconds_to_unlock(test_id, unix_now()))
+    {
+        return Ok(PartView::Locked(seconds_to_unlock));
+    }
+    let test_responses = get_responses(session, state, ResponseKind::Test).await?;
+    let count_canceled = get_count_canceled(session).await;
+    let test_finished = get_test_finished(session).await;
+
+    let questions_stats = if test_finished {
+        let rows = db::get_question_stats(&state.db).await?;
+        question_stats_from_rows(&rows, &state.questions_db)
+    } else {
+        QuestionStatsData::new()
+    };
+
+    let mut timer: Option<TimedAttempt> = session.get(GT_TIMER_KEY).await.unwrap_or_default();
+    let seconds_left = match timer.as_mut() {
+        Some(timer) if !test_finished => {
+            let now = unix_now();
+            if timer.start_part(test_id, now) {
+                session
+                    .insert(GT_TIMER_KEY, &timer)
+                    .await
+                    .unwrap_or_default();
+            }
+            timer
+                .deadline(test_id)
+                .map(|deadline| deadline.saturating_sub(now))
+        }
+        _ => None,
+    };
+
+    let shuffle_seed = if shuffle {
+        get_shuffle_seed(session).await
+    } else {
+        None
+    };
+    let part_state = get_part_state(
+        test_part,
+        &test_responses,
+        count_canceled,
+        &questions_stats,
+        shuffle_seed,
+    );
+    if !test_finished {
+        let view = analytics::event(EventKind::PartView, Some(test_id));
+        analytics::record(state, session, &[view]).await;
+    }
+
+    Ok(PartView::Open {
+        part_state,
+        test_finished,
+        seconds_left,
+    })
+}
+
 async fn get_part(
     State(state): State<AppState>,
     session: Session,
     Path(id): Path<usize>,
 ) -> Result<impl IntoResponse, Error> {
     let test_id = id.to_string();
-    let test_part = state
-        .giga_test
-        .get(&test_id)
-        .ok_or(crate::Error::NotFound)?;
-    let test_responses: UserResponseData = session
-        .get(GT_RESP_KEY)
+    match load_part(&state, &session, &test_id, true).await? {
+        PartView::Locked(seconds_to_unlock) => {
+            Ok(Html(PartLocked::new(test_id, seconds_to_unlock).render()?))
+        }
+        PartView::Open {
+            part_state,
+            test_finished,
+            seconds_left,
+        } => Ok(Html(
+            Part::new(&part_state, test_finished, seconds_left).render()?,
+        )),
+    }
+}
+
+async fn get_about() -> Result<impl IntoResponse, Error> {
+    Ok(Html(About::new().render()?))
+}
+
+/// Answers to questions can't change once the test is finished, or while
+/// their part is locked or past deadline
+async fn check_answers_accepted<'a>(
+    state: &AppState,
+    session: &Session,
+    question_ids: impl Iterator<Item = &'a String> + Clone,
+) -> Result<(), Error> {
+    if get_test_finished(session).await {
+        return Err(Error::TestFinished);
+    }
+    if let Some(schedule) = &state.schedule {
+        let now = unix_now();
+        for part_id in question_ids.clone().filter_map(|id| question_part_id(id)) {
+            if schedule.seconds_to_unlock(part_id, now).is_some() {
+                return Err(Error::PartLocked);
+            }
+            if schedule.seconds_to_deadline(part_id, now) == Some(0) {
+                return Err(Error::TimeExpired);
+            }
+        }
+    }
+
+    let timer: Option<TimedAttempt> = session.get(GT_TIMER_KEY).await.unwrap_or_default();
+    if let Some(mut timer) = timer {
+        let now = unix_now();
+        // Clients that answer without opening the part page (API, autosave)
+        // start its clock with their first answer
+        let mut started = false;
+        for part_id in question_ids.clone().filter_map(|id| question_part_id(id)) {
+            started |= timer.start_part(part_id, now);
+        }
+        if started {
+            session
+                .insert(GT_TIMER_KEY, &timer)
+                .await
+                .unwrap_or_default();
+        }
+        let mut question_ids = question_ids;
+        let expired = question_ids.any(|question_id| {
+            question_part_id(question_id)
+                .and_then(|part_id| timer.deadline(part_id))
+                .is_none_or(|deadline| now > deadline + TIME_LIMIT_GRACE_SECS)
+        });
+        if expired {
+            return Err(Error::TimeExpired);
+        }
+    }
+    Ok(())
+}
+
+/// Save answers to the test, unless their part is locked or past deadline
+pub(crate) async fn save_test_answers(
+    state: &AppState,
+    session: &Session,
+    new_responses: &UserResponseData,
+) -> Result<(), Error> {
+    check_answers_accepted(state, session, new_responses.keys()).await?;
+
+    let events = if analytics::enabled(state) {
+        let previous = get_responses(session, state, ResponseKind::Test).await?;
+        analytics::answer_events(&previous, new_responses)
+    } else {
+        Vec::new()
+    };
+    save_responses(session, state, ResponseKind::Test, new_responses).await?;
+    analytics::record(state, session, &events).await;
+
+    Ok(())
+}
+
+/// Remove answer to a test question, so it counts as not answered again
+pub(crate) async fn clear_test_answer(
+    state: &AppState,
+    session: &Session,
+    question_id: &str,
+) -> Result<(), Error> {
+    if !state.questions_db.contains_key(question_id) {
+        return Err(Error::UnknownQuestion(question_id.to_string()));
+    }
+    let question_id = question_id.to_string();
+    check_answers_accepted(state, session, std::iter::once(&question_id)).await?;
+    let previous = get_responses(session, state, ResponseKind::Test).await?;
+    delete_response(session, state, ResponseKind::Test, &question_id).await?;
+    if previous.contains_key(&question_id) {
+        let event = Event {
+            kind: EventKind::AnswerChange,
+            part_id: question_part_id(&question_id).map(String::from),
+            question_id: Some(question_id),
+        };
+        analytics::record(state, session, &[event]).await;
+    }
+    Ok(())
+}
+
+async fn post_answers(
+    State(state): State<AppState>,
+    session: Session,
+    Form(mut form): Form<HashMap<String, String>>,
+) -> Result<Redirect, Error> {
+    // "wyczyść" button saves the other answers too, so picks aren't lost
+    let cleared = form.remove(CLEAR_ANSWER_FIELD);
+    if let Some(question_id) = &cleared {
+        form.remove(question_id);
+    }
+    validate_form_data(&form, &state.giga_test)?;
+    let form_data = canonical_form_data(&form, &state.giga_test, get_shuffle_seed(&session).await);
+    let new_responses: UserResponseData = responses_from_form_data(&form_data, &state.questions_db);
+    save_test_answers(&state, &session, &new_responses).await?;
+    let Some(question_id) = cleared else {
+        return Ok(Redirect::to("/"));
+    };
+    clear_test_answer(&state, &session, &question_id).await?;
+    let part_id = question_part_id(&question_id).unwrap_or_default();
+    Ok(Redirect::to(&format!("/czesc-{part_id}#{question_id}")))
+}
+
+/// Save answer to a single question as soon as it's picked, or clear it
+/// when sent as `wyczysc` field. Called by autosave script on part page,
+/// form submit still saves all answers.
+async fn post_single_answer(
+    State(state): State<AppState>,
+    session: Session,
+    form: Form<HashMap<String, String>>,
+) -> Result<Json<SavedAnswer>, Error> {
+    let mut fields = form.0.iter();
+    let (Some((question_id, value)), None) = (fields.next(), fields.next()) else {
+        return Err(Error::WrongSize);
+    };
+    if question_id == CLEAR_ANSWER_FIELD {
+        clear_test_answer(&state, &session, value).await?;
+        return Ok(Json(SavedAnswer {
+            question_id: value.clone(),
+            answer: None,
+        }));
+    }
+    validate_form_data(&form.0, &state.giga_test)?;
+    let answer = value.chars().next().ok_or(Error::WrongSize)?;
+    let form_data =
+        canonical_form_data(&form.0, &state.giga_test, get_shuffle_seed(&session).await);
+    let new_responses: UserResponseData = responses_from_form_data(&form_data, &state.questions_db);
+    save_test_answers(&state, &session, &new_responses).await?;
+    Ok(Json(SavedAnswer {
+        question_id: question_id.clone(),
+        answer: Some(answer),
+    }))
+}
+
+async fn submit_toggle_canceled(session: Session) -> Redirect {
+    let count_canceled: CountCanceled = session
+        .get(GT_COUNT_CANCELED_KEY)
         .await
         .unwrap_or_default()
         .unwrap_or_default();
-    let count_canceled: CountCanceled = session
-        .get(GT_COUNT_CANCELED_KEY)
+    session
+        .insert(GT_COUNT_CANCELED_KEY, !count_canceled.0)
+        .await
+        .unwrap_or_default();
+    Redirect::to("/")
+}
+
+/// Finish the attempt: archive it, add its answers to statistics and save
+/// shareable result. Finishing already finished attempt does nothing.
+pub(crate) async fn finish_test(state: &AppState, session: &Session) -> Result<(), Error> {
+    if get_test_finished(session).await {
+        return Ok(());
+    }
+    // Requests finishing the same attempt at once all see it unfinished in
+    // their copy of session - only the one that claims it goes on, others
+    // leave session alone so they don't overwrite what it saves
+    let player_id = get_player_id(session).await;
+    let attempt_id: String = session
+        .get(GT_ATTEMPT_KEY)
         .await
         .unwrap_or_default()
         .unwrap_or_default();
+    if !db::claim_finished_attempt(&state.db, &player_id, &attempt_id).await? {
+        return Ok(());
+    }
+    let test_responses = get_responses(session, state, ResponseKind::Test).await?;
+    session
+        .insert(GT_FINISHED_KEY, true)
+        .await
+        .unwrap_or_default();
+    analytics::record(state, session, &[analytics::event(EventKind::Submit, None)]).await;
+
+    if let Err(e) = db::record_question_stats(&state.db, &test_responses).await {
+        tracing::warn!("Failed to record question statistics: {e}");
+    }
+
+    let count_canceled = get_count_canceled(session).await;
+    let index_tests_state =
+        get_index_tests_state(&state.giga_test, &test_responses, count_canceled);
+    let totals = get_index_totals(&index_tests_state);
+    let timer: Option<TimedAttempt> = session.get(GT_TIMER_KEY).await.unwrap_or_default();
+    if let Err(e) = db::insert_attempt(
+        &state.db,
+        &player_id,
+        &totals,
+        count_canceled,
+        &test_responses,
+        timer.as_ref().map(|t| &t.limit),
+        get_shuffle_seed(session).await,
+    )
+    .await
+    {
+        tracing::warn!("Failed to archive attempt: {e}");
+    }
+
+    let result_id = uuid::Uuid::new_v4().simple().to_string()[..RESULT_ID_LEN].to_string();
+    let snapshot = ResultSnapshot::new(count_canceled, &totals, &index_tests_state);
+    match db::insert_result(&state.db, &result_id, &snapshot).await {
+        Ok(()) => session
+            .insert(GT_RESULT_KEY, result_id)
+            .await
+            .unwrap_or_default(),
+        Err(e) => tracing::warn!("Failed to save result snapshot: {e}"),
+    }
+    Ok(())
+}
+
+async fn submit_test(State(state): State<AppState>, session: Session) -> Result<Redirect, Error> {
+    finish_test(&state, &session).await?;
+    Ok(Redirect::to("/"))
+}
+
+/// Start a new attempt, dropping answers of the current one
+pub(crate) async fn restart_test(
+    state: &AppState,
+    session: &Session,
+    time_limit: Option<TimeLimit>,
+    shuffle: bool,
+) -> Result<(), Error> {
+    let timer = time_limit.map(|limit| TimedAttempt::new(limit, unix_now()));
+    session
+        .insert(GT_TIMER_KEY, timer)
+        .await
+        .unwrap_or_default();
+    let shuffle_seed = shuffle.then(|| uuid::Uuid::new_v4().as_u64_pair().0);
+    session
+        .insert(GT_SHUFFLE_KEY, shuffle_seed)
+        .await
+        .unwrap_or_default();
+    clear_responses(session, state, ResponseKind::Test).await?;
+    new_attempt_id(session).await;
+    analytics::record(
+        state,
+        session,
+        &[analytics::event(EventKind::NewTest, None)],
+    )
+    .await;
+    session
+        .insert(GT_FINISHED_KEY, false)
+        .await
+        .unwrap_or_default();
+    session
+        .insert(GT_PUBLISHED_KEY, false)
+        .await
+        .unwrap_or_default();
+    let _ = session.remove::<String>(GT_RESULT_KEY).await;
+    Ok(())
+}
+
+async fn start_new_test(
+    State(state): State<AppState>,
+    session: Session,
+    form: Result<Form<NewTestForm>, FormRejection>,
+) -> Result<Redirect, Error> {
+    let Form(form) = form.map_err(|_| Error::IllegalCharacters)?;
+    restart_test(&state, &session, form.time_limit()?, form.tasuj).await?;
+    Ok(Redirect::to("/"))
+}
+
+async fn get_history(
+    State(state): State<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, Error> {
+    let player_id = get_player_id(&session).await;
+    let attempts = attempts_progress(db::get_attempts(&state.db, &player_id).await?);
+    Ok(Html(History::new(&attempts).render()?))
+}
+
+async fn get_archived_attempt(
+    State(state): State<AppState>,
+    session: Session,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, Error> {
+    let player_id = get_player_id(&session).await;
+    let mut attempt = db::get_attempt(&state.db, &player_id, id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    regrade_responses(&mut attempt.responses, &state.questions_db);
+    let index_tests_state =
+        get_index_tests_state(&state.giga_test, &attempt.responses, attempt.count_canceled);
+    Ok(Html(
+        ArchivedAttempt::new(&attempt, &index_tests_state).render()?,
+    ))
+}
+
+async fn get_archived_attempt_part(
+    State(state): State<AppState>,
+    session: Session,
+    Path((id, part_id)): Path<(i64, usize)>,
+) -> Result<impl IntoResponse, Error> {
+    let test_part = state
+        .giga_test
+        .get(&part_id.to_string())
+        .ok_or(Error::NotFound)?;
+    let player_id = get_player_id(&session).await;
+    let mut attempt = db::get_attempt(&state.db, &player_id, id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    regrade_responses(&mut attempt.responses, &state.questions_db);
+    let rows = db::get_question_stats(&state.db).await?;
+    let questions_stats = question_stats_from_rows(&rows, &state.questions_db);
+    let part_state = get_part_state(
+        test_part,
+        &attempt.responses,
+        attempt.count_canceled,
+        &questions_stats,
+        attempt.shuffle_seed,
+    );
+    Ok(Html(Part::archived(&part_state, &attempt).render()?))
+}
+
+async fn get_leaderboard(
+    State(state): State<AppState>,
+    session: Session,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let count_canceled = match query.anulowane {
+        Some(count_canceled) => count_canceled,
+        None => {
+            let count_canceled: CountCanceled = session
+                .get(GT_COUNT_CANCELED_KEY)
+                .await
+                .unwrap_or_default()
+                .unwrap_or_default();
+            count_canceled.0
+        }
+    };
+    let page = query.strona.unwrap_or(1).max(1);
+    let (entries, has_next_page) = db::get_leaderboard(
+        &state.db,
+        query.okres,
+        count_canceled,
+        Some(query.na_czas),
+        false,
+        page - 1,
+    )
+    .await?;
+    let leaderboard = Leaderboard::new(
+        &entries,
+        query.okres,
+        count_canceled,
+        query.na_czas,
+        page,
+        has_next_page,
+    );
+    Ok(Html(leaderboard.render()?))
+}
+
+async fn publish_to_leaderboard(
+    State(state): State<AppState>,
+    session: Session,
+    Form(form): Form<LeaderboardForm>,
+) -> Result<Redirect, Error> {
     let test_finished: TestFinished = session
         .get(GT_FINISHED_KEY)
         .await
         .unwrap_or_default()
         .unwrap_or_default();
+    if !test_finished.0 {
+        return Err(Error::TestNotFinished);
+    }
+    let leaderboard_published: LeaderboardPublished = session
+        .get(GT_PUBLISHED_KEY)
+        .await
+        .unwrap_or_default()
+        .unwrap_or_default();
+    if leaderboard_published.0 {
+        return Ok(Redirect::to("/ranking"));
+    }
+    let nickname = validate_nickname(&form.nickname)?;
 
-    let part_state = get_part_state(test_part, &test_responses, count_canceled.0);
+    // Scores frozen at finish time, so answers saved later can't improve them
+    let result_id: Option<String> = session.get(GT_RESULT_KEY).await.unwrap_or_default();
+    let snapshot = match result_id {
+        Some(result_id) => db::get_result(&state.db, &result_id).await?,
+        None => None,
+    };
+    let (without_canceled, with_canceled, place) = match snapshot {
+        Some(snapshot) => (
+            snapshot.totals.answered_good_q_without_canceled,
+            snapshot.totals.answered_good_q_with_canceled,
+            snapshot.totals.place,
+        ),
+        None => {
+            let player_id = get_player_id(&session).await;
+            let attempt = db::get_attempts(&state.db, &player_id)
+                .await?
+                .pop()
+                .ok_or(Error::TestNotFinished)?;
+            (
+                attempt.answered_good_q_without_canceled,
+                attempt.answered_good_q_with_canceled,
+                attempt.place,
+            )
+        }
+    };
+    let timer: Option<TimedAttempt> = session.get(GT_TIMER_KEY).await.unwrap_or_default();
+    db::insert_leaderboard_entry(
+        &state.db,
+        &nickname,
+        without_canceled,
+        with_canceled,
+        place,
+        timer.is_some(),
+    )
+    .await?;
+    session
+        .insert(GT_PUBLISHED_KEY, true)
+        .await
+        .unwrap_or_default();
+    Ok(Redirect::to("/ranking"))
+}
 
-    Ok(Html(Part::new(&part_state, test_finished.0).render()?))
+fn practice_available(state: &AppState, part_id: &str) -> bool {
+    state
+        .schedule
+        .as_ref()
+        .is_none_or(|schedule| schedule.practice_available(part_id, unix_now()))
 }
 
-async fn get_about() -> Result<impl IntoResponse, Error> {
-    Ok(Html(About::new().render()?))
+async fn get_practice_index(
+    State(state): State<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, Error> {
+    let practice_responses = get_responses(&session, &state, ResponseKind::Practice).await?;
+    let index_tests_state: Vec<_> =
+        get_index_tests_state(&state.giga_test, &practice_responses, true)
+            .into_iter()
+            .filter(|elem| practice_available(&state, &elem.test_id))
+            .collect();
+    Ok(Html(PracticeIndex::new(&index_tests_state).render()?))
 }
 
-async fn post_answers(
+async fn get_practice_part(
     State(state): State<AppState>,
     session: Session,
-    form: Form<HashMap<String, String>>,
-) -> Redirect {
-    let new_responses: UserResponseData = responses_from_form_data(&form.0, &state.questions_db);
+    Path(id): Path<usize>,
+) -> Result<impl IntoResponse, Error> {
+    let test_id = id.to_string();
+    let test_part = state.giga_test.get(&test_id).ok_or(Error::NotFound)?;
+    if !practice_available(&state, &test_id) {
+        return Err(Error::PartLocked);
+    }
+    let practice_responses = get_responses(&session, &state, ResponseKind::Practice).await?;
+    let part_state = get_part_state(
+        test_part,
+        &practice_responses,
+        true,
+        &QuestionStatsData::new(),
+        get_shuffle_seed(&session).await,
+    );
+    Ok(Html(PracticePart::new(&part_state).render()?))
+}
+
+async fn post_practice_answer(
+    State(state): State<AppState>,
+    session: Session,
+    Form(form): Form<PracticeAnswerForm>,
+) -> Result<Redirect, Error> {
+    let question_id = form.sprawdz;
+    let part_id = question_part_id(&question_id)
+        .ok_or(Error::NotFound)?
+        .to_string();
+    if !practice_available(&state, &part_id) {
+        return Err(Error::PartLocked);
+    }
+    let answer: HashMap<String, String> = form
+        .answers
+        .into_iter()
+        .filter(|(id, _)| *id == question_id)
+        .collect();
+    validate_form_data(&answer, &state.giga_test)?;
+    let answer = canonical_form_data(&answer, &state.giga_test, get_shuffle_seed(&session).await);
+    let new_responses = responses_from_form_data(&answer, &state.questions_db);
+    save_responses(&session, &state, ResponseKind::Practice, &new_responses).await?;
+    Ok(Redirect::to(&format!(
+        "/cwiczenia/czesc-{part_id}#{question_id}"
+    )))
+}
+
+async fn start_new_practice(
+    State(state): State<AppState>,
+    session: Session,
+) -> Result<Redirect, Error> {
+    clear_responses(&session, &state, ResponseKind::Practice).await?;
+    Ok(Redirect::to("/cwiczenia"))
+}
+
+fn quick_quiz_parts(state: &AppState) -> Vec<&str> {
+    state
+        .giga_test
+        .iter()
+        .map(|(part_id, _)| part_id.as_str())
+        .filter(|part_id| practice_available(state, part_id))
+        .collect()
+}
+
+async fn get_quick_quiz(
+    State(state): State<AppState>,
+    session: Session,
+    Query(query): Query<QuickQuizQuery>,
+) -> Result<Response, Error> {
+    let saved_draw: Option<QuickQuizDraw> =
+        session.get(GT_QUICK_QUIZ_KEY).await.unwrap_or_default();
+    let parts = quick_quiz_parts(&state);
+    let Some(seed) = query.ziarno else {
+        return match saved_draw {
+            Some(draw) => Ok(Redirect::to(&draw.url()).into_response()),
+            None => Ok(Html(QuickQuizNew::new(&parts).render()?).into_response()),
+        };
+    };
+    let draw = QuickQuizDraw {
+        seed,
+        count: query.pytan.unwrap_or(QUICK_QUIZ_DEFAULT_QUESTIONS),
+        part: query.czesc.filter(|part| !part.is_empty()),
+    };
+    if !(1..=QUICK_QUIZ_MAX_QUESTIONS).contains(&draw.count) {
+        return Err(Error::WrongSize);
+    }
+    let quiz = draw_quick_quiz(&state.giga_test, &draw, |part_id| {
+        practice_available(&state, part_id)
+    })
+    .ok_or(Error::NotFound)?;
 
-    if !new_responses.is_empty() {
-        let test_responses: UserResponseData = session
-            .get(GT_RESP_KEY)
+    // Opening a different quiz, e.g. from a shared link, replaces the saved one
+    let (test_responses, finished) = if saved_draw.as_ref() == Some(&draw) {
+        let test_responses = get_responses(&session, &state, ResponseKind::QuickQuiz).await?;
+        let finished: bool = session
+            .get(GT_QUICK_QUIZ_FINISHED_KEY)
             .await
             .unwrap_or_default()
             .unwrap_or_default();
-        let all_responses: UserResponseData =
-            test_responses.into_iter().chain(new_responses).collect();
+        (test_responses, finished)
+    } else {
         session
-            .insert(GT_RESP_KEY, all_responses)
+            .insert(GT_QUICK_QUIZ_KEY, &draw)
             .await
             .unwrap_or_default();
-    }
+        clear_responses(&session, &state, ResponseKind::QuickQuiz).await?;
+        session
+            .insert(GT_QUICK_QUIZ_FINISHED_KEY, false)
+            .await
+            .unwrap_or_default();
+        (UserResponseData::new(), false)
+    };
 
-    Redirect::to("/")
+    let part_state = get_part_state(
+        &quiz,
+        &test_responses,
+        true,
+        &QuestionStatsData::new(),
+        get_shuffle_seed(&session).await,
+    );
+    let tally = tally_quick_quiz(&quiz, &test_responses);
+    Ok(
+        Html(QuickQuiz::new(&part_state, &draw, &tally, finished, &parts).render()?)
+            .into_response(),
+    )
 }
 
-async fn submit_toggle_canceled(session: Session) -> Redirect {
-    let count_canceled: CountCanceled = session
-        .get(GT_COUNT_CANCELED_KEY)
+async fn start_quick_quiz(Form(form): Form<QuickQuizForm>) -> Result<Redirect, Error> {
+    if !(1..=QUICK_QUIZ_MAX_QUESTIONS).contains(&form.pytan) {
+        return Err(Error::WrongSize);
+    }
+    let draw = QuickQuizDraw {
+        seed: uuid::Uuid::new_v4().as_u64_pair().0,
+        count: form.pytan,
+        part: Some(form.czesc).filter(|part| !part.is_empty()),
+    };
+    Ok(Redirect::to(&draw.url()))
+}
+
+async fn post_quick_quiz_answers(
+    State(state): State<AppState>,
+    session: Session,
+    form: Form<HashMap<String, String>>,
+) -> Result<Redirect, Error> {
+    let draw: Option<QuickQuizDraw> = session.get(GT_QUICK_QUIZ_KEY).await.unwrap_or_default();
+    let Some(draw) = draw else {
+        return Ok(Redirect::to("/szybki-test"));
+    };
+    let finished: bool = session
+        .get(GT_QUICK_QUIZ_FINISHED_KEY)
         .await
         .unwrap_or_default()
         .unwrap_or_default();
+    if finished {
+        return Ok(Redirect::to(&draw.url()));
+    }
+    let quiz = draw_quick_quiz(&state.giga_test, &draw, |part_id| {
+        practice_available(&state, part_id)
+    })
+    .ok_or(Error::NotFound)?;
+    validate_form_data(&form.0, &state.giga_test)?;
+    let form_data =
+        canonical_form_data(&form.0, &state.giga_test, get_shuffle_seed(&session).await);
+    let quiz_questions: Vec<&str> = quiz.get_questions().iter().map(|q| q.id.as_str()).collect();
+    let test_responses: UserResponseData =
+        responses_from_form_data(&form_data, &state.questions_db)
+            .into_iter()
+            .filter(|(question_id, _)| quiz_questions.contains(&question_id.as_str()))
+            .collect();
+    replace_responses(&session, &state, ResponseKind::QuickQuiz, &test_responses).await?;
     session
-        .insert(GT_COUNT_CANCELED_KEY, !count_canceled.0)
+        .insert(GT_QUICK_QUIZ_FINISHED_KEY, true)
         .await
         .unwrap_or_default();
-    Redirect::to("/")
+    Ok(Redirect::to(&draw.url()))
 }
 
-async fn submit_test(session: Session) -> Redirect {
-    session
-        .insert(GT_FINISHED_KEY, true)
+async fn get_export(
+    State(state): State<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, Error> {
+    let secret_key = state.secret_key.as_ref().ok_or(Error::NotFound)?;
+    let progress = session_export(&session, &state).await?;
+    let code = progress_code(&progress, &state.questions_db, secret_key)?;
+    Ok(Html(Export::new(&code).render()?))
+}
+
+async fn get_export_file(
+    State(state): State<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, Error> {
+    let secret_key = state.secret_key.as_ref().ok_or(Error::NotFound)?;
+    let file = progress_file(session_export(&session, &state).await?, secret_key)?;
+    Ok((
+        [
+            (CONTENT_TYPE, "application/json"),
+            (
+                CONTENT_DISPOSITION,
+                "attachment; filename=\"giga-test-postepy.json\"",
+            ),
+        ],
+        file,
+    ))
+}
+
+async fn session_export(session: &Session, state: &AppState) -> Result<ExportedProgress, Error> {
+    let test_responses = get_responses(session, state, ResponseKind::Test).await?;
+    let test_finished: TestFinished = session
+        .get(GT_FINISHED_KEY)
         .await
+        .unwrap_or_default()
         .unwrap_or_default();
-    Redirect::to("/")
+    let timer: Option<TimedAttempt> = session.get(GT_TIMER_KEY).await.unwrap_or_default();
+    let count_canceled: CountCanceled = session
+        .get(GT_COUNT_CANCELED_KEY)
+        .await
+        .unwrap_or_default()
+        .unwrap_or_default();
+    export_progress(
+        &test_responses,
+        test_finished.0,
+        count_canceled.0,
+        timer.as_ref(),
+        get_shuffle_seed(session).await,
+    )
+}
+
+fn upload_error(e: MultipartError) -> Error {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        Error::PayloadTooLarge
+    } else {
+        Error::InvalidSignature
+    }
 }
 
-async fn start_new_test(session: Session) -> Redirect {
+/// Accepts either a code typed into the form, or an uploaded file
+async fn post_import(
+    State(state): State<AppState>,
+    session: Session,
+    mut multipart: Multipart,
+) -> Result<Redirect, Error> {
+    let secret_key = state.secret_key.as_ref().ok_or(Error::NotFound)?;
+    let mut input = String::new();
+    while let Some(field) = multipart.next_field().await.map_err(upload_error)? {
+        if !matches!(field.name(), Some("kod" | "plik")) {
+            continue;
+        }
+        let text = field.text().await.map_err(upload_error)?;
+        if !text.trim().is_empty() {
+            input = text;
+        }
+    }
+    let progress = import_progress(&input, &state.questions_db, secret_key)?;
+    let test_responses = responses_from_export(&progress, &state.questions_db)?;
+
+    // Imported progress replaces everything in session. Finished attempt is
+    // marked as published, so the same result can't be put in ranking twice.
+    session.cycle_id().await.unwrap_or_default();
+    for key in PROGRESS_KEYS {
+        let _ = session.remove::<Value>(key).await;
+    }
+    for (key, _) in SESSION_RESPONSE_KEYS {
+        let _ = session.remove::<Value>(key).await;
+    }
+    let player_id = get_player_id(&session).await;
+    db::delete_player_responses(&state.db, &player_id).await?;
+    replace_responses(&session, &state, ResponseKind::Test, &test_responses).await?;
+    new_attempt_id(&session).await;
     session
-        .insert(GT_RESP_KEY, UserResponseData::new())
+        .insert(GT_SHUFFLE_KEY, progress.shuffle_seed)
         .await
         .unwrap_or_default();
     session
-        .insert(GT_FINISHED_KEY, false)
+        .insert(GT_FINISHED_KEY, progress.finished)
         .await
         .unwrap_or_default();
-    Redirect::to("/")
+    session
+        .insert(GT_COUNT_CANCELED_KEY, progress.count_canceled)
+        .await
+        .unwrap_or_default();
+    session
+        .insert(GT_PUBLISHED_KEY, progress.finished)
+        .await
+        .unwrap_or_default();
+    Ok(Redirect::to("/"))
+}
+
+async fn get_result(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let snapshot = db::get_result(&state.db, &id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(Html(
+        SharedResult::new(
+            &id,
+            &snapshot,
+            state.public_url.as_deref().unwrap_or_default(),
+        )
+        .render()?,
+    ))
 }
 
-pub fn routes() -> Router<AppState> {
+async fn get_result_card(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let snapshot = db::get_result(&state.db, &id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let card = tokio::task::spawn_blocking(move || render_result_card(&snapshot)).await??;
+    Ok((
+        [
+            (CONTENT_TYPE, "image/png"),
+            // Snapshots never change
+            (CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        card,
+    ))
+}
+
+pub fn routes(state: &AppState) -> Router<AppState> {
     Router::new()
         .route("/", get(get_index))
         .route("/czesc-{id}", get(get_part))
         .route("/o-co-chodzi-jakby", get(get_about))
         .route("/odpowiedzi", post(post_answers))
+        .route("/odpowiedz", post(post_single_answer))
         .route("/licz-anulowane", post(submit_toggle_canceled))
         .route("/zakoncz", post(submit_test))
         .route("/od-nowa", post(start_new_test))
+        .route(
+            "/ranking",
+            get(get_leaderboard).post(publish_to_leaderboard),
+        )
+        .route("/cwiczenia", get(get_practice_index))
+        .route("/cwiczenia/czesc-{id}", get(get_practice_part))
+        .route("/cwiczenia/odpowiedz", post(post_practice_answer))
+        .route("/cwiczenia/od-nowa", post(start_new_practice))
+        .route("/eksport", get(get_export))
+        .route("/eksport/plik", get(get_export_file))
+        .route("/import", post(post_import))
+        .route("/wynik/{id}", get(get_result))
+        .route("/wynik/{id}/karta.png", get(get_result_card))
+        .route("/szybki-test", get(get_quick_quiz))
+        .route("/szybki-test/nowy", post(start_quick_quiz))
+        .route("/szybki-test/odpowiedzi", post(post_quick_quiz_answers))
+        .route("/historia", get(get_history))
+        .route("/historia/{id}", get(get_archived_attempt))
+        .route(
+            "/historia/{id}/czesc-{part_id}",
+            get(get_archived_attempt_part),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.max_body_bytes,
+            csrf::protect,
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::DEFAULT_MAX_BODY_BYTES;
+    use crate::giga_test::{content_hash, get_giga_test};
+    use crate::models::responses;
+    use crate::sessions::{MemoryStore, SessionBackend, SessionBackendConfig};
+    use std::sync::Arc;
+    use tower_sessions_sqlx_store::sqlx::SqlitePool;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        db::migrate(&pool).await.unwrap();
+        pool
+    }
+
+    /// Replica of the app, with its own SQLite pool and given session store
+    fn replica(pool: SqlitePool, session_store: SessionBackend) -> AppState {
+        let giga_test = get_giga_test(&|s| s.to_string());
+        let questions_db = giga_test.get_correct_answers();
+        AppState {
+            content_hash: content_hash(&questions_db),
+            giga_test,
+            questions_db,
+            db: pool,
+            admin_password: None,
+            schedule: None,
+            secret_key: None,
+            public_url: None,
+            session_store,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            analytics_retention_days: None,
+        }
+    }
+
+    /// Answers saved through one replica are seen through the other, in
+    /// later requests of the same session
+    async fn check_replicas_share_answers(first: &AppState, second: &AppState) {
+        let store = Arc::new(first.session_store.clone());
+        let session = Session::new(None, store.clone(), None);
+        save_test_answers(first, &session, &responses(&[("q1_1_0", 'A')]))
+            .await
+            .unwrap();
+        session.save().await.unwrap();
+
+        let session = Session::new(session.id(), store.clone(), None);
+        let test_responses = get_responses(&session, second, ResponseKind::Test)
+            .await
+            .unwrap();
+        assert_eq!(test_responses["q1_1_0"].user_answer, 'A');
+        clear_test_answer(second, &session, "q1_1_0").await.unwrap();
+        session.save().await.unwrap();
+
+        let session = Session::new(session.id(), store, None);
+        let test_responses = get_responses(&session, first, ResponseKind::Test)
+            .await
+            .unwrap();
+        assert!(test_responses.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_replicas_share_answers_with_shared_sessions() {
+        // Stands in for Postgres or Redis, shared by replicas that each have
+        // their own SQLite file
+        let store = SessionBackend::Memory(MemoryStore::default());
+        let first = replica(test_pool().await, store.clone());
+        let second = replica(test_pool().await, store);
+        check_replicas_share_answers(&first, &second).await;
+    }
+
+    #[tokio::test]
+    async fn test_replicas_share_answers_with_sqlite_sessions() {
+        let pool = test_pool().await;
+        let store = SessionBackend::connect(&SessionBackendConfig::Sqlite, &pool)
+            .await
+            .unwrap();
+        let first = replica(pool.clone(), store.clone());
+        let second = replica(pool, store);
+        check_replicas_share_answers(&first, &second).await;
+    }
 }
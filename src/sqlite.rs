@@ -0,0 +1,61 @@
+use std::str::FromStr;
+use std::time::Duration;
+use tower_sessions_sqlx_store::sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous,
+};
+use tower_sessions_sqlx_store::sqlx::{self, SqlitePool};
+
+const MEMORY_URL: &str = "sqlite::memory:";
+
+/// How SQLite database is opened, see `env::sqlite_config`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SqliteConfig {
+    /// Connection URL, `sqlite:` followed by path
+    pub(crate) url: String,
+    pub(crate) max_connections: u32,
+    /// Write-ahead log lets readers carry on while another connection writes
+    pub(crate) wal: bool,
+    /// How long a connection waits for a lock held by another one
+    pub(crate) busy_timeout: Duration,
+}
+
+impl SqliteConfig {
+    pub(crate) fn is_memory(&self) -> bool {
+        self.url.eq_ignore_ascii_case(MEMORY_URL)
+    }
+
+    /// In-memory database lives as long as its connection, and each
+    /// connection would get a separate one
+    pub(crate) fn pool_size(&self) -> u32 {
+        if self.is_memory() {
+            1
+        } else {
+            self.max_connections
+        }
+    }
+
+    pub(crate) async fn connect(&self) -> Result<SqlitePool, sqlx::Error> {
+        let mut options = SqliteConnectOptions::from_str(&self.url)?
+            .create_if_missing(true)
+            .busy_timeout(self.busy_timeout);
+        if !self.is_memory() {
+            // Journal mode is stored in the file, so it's always set
+            // explicitly to be able to turn WAL off again
+            options = if self.wal {
+                // With WAL, NORMAL is still safe from corruption, only last
+                // commits can be lost on power failure
+                options
+                    .journal_mode(SqliteJournalMode::Wal)
+                    .synchronous(SqliteSynchronous::Normal)
+            } else {
+                options
+                    .journal_mode(SqliteJournalMode::Delete)
+                    .synchronous(SqliteSynchronous::Full)
+            };
+        }
+        SqlitePoolOptions::new()
+            .max_connections(self.pool_size())
+            .connect_with(options)
+            .await
+    }
+}